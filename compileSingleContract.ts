@@ -3,6 +3,7 @@ import fs from 'fs-extra';
 import path from 'path';
 import glob from 'glob';
 import util from 'node:util';
+import os from 'os';
 const execPromise = util.promisify(exec);
 
 const createFileWithDirectoriesSync = (filePath: string, data: string) => {
@@ -44,16 +45,24 @@ const compileContract = async (contractPath: string) => {
   });
 };
 
-const copyArtifacts = async (contractPath: string, contractName: string) => {
+// artifacts are saved as artifactName, so code of older revision can be kept next to the current one
+const copyArtifacts = async (contractPath: string, contractName: string, artifactName: string = contractName) => {
   const artifactsCompileOutputPath = path.join(contractPath, 'target', 'ink');
   const artifactsOutputPath = path.join('artifacts');
   console.log('Copying artifacts...');
   fs.ensureDirSync(artifactsOutputPath);
   fs.copyFileSync(
     path.join(artifactsCompileOutputPath, `${contractName}.contract`),
-    path.join(artifactsOutputPath, `${contractName}.contract`)
+    path.join(artifactsOutputPath, `${artifactName}.contract`)
   );
-  fs.copyFileSync(path.join(artifactsCompileOutputPath, `metadata.json`), path.join(artifactsOutputPath, `${contractName}.json`));
+  fs.copyFileSync(path.join(artifactsCompileOutputPath, `metadata.json`), path.join(artifactsOutputPath, `${artifactName}.json`));
+};
+
+// checks out revision to a temporary worktree, so older code can be compiled without touching the working tree
+const checkoutRevision = async (revision: string) => {
+  const worktreePath = path.join(os.tmpdir(), `stable-coin-project-${revision}`);
+  await execPromise(`git worktree add --force --detach ${worktreePath} ${revision}`);
+  return worktreePath;
 };
 
 const argvObj = process.argv.reduce((acc, val, index) => {
@@ -64,11 +73,15 @@ const argvObj = process.argv.reduce((acc, val, index) => {
 
 (async (args: Record<string, unknown>) => {
   if (require.main !== module) return;
-  const contractsRootPath = './contracts';
   const contractName = (args['name'] as string) ?? process.argv[2];
-  const contractFolderPath = getContractsFolderPath(contractsRootPath, contractName);
+  // --rev builds the contract from git revision, --as names its artifacts, e.g. for upgrade tests
+  const revision = args['rev'] as string | undefined;
+  const artifactName = (args['as'] as string) ?? contractName;
+  const rootPath = revision ? await checkoutRevision(revision) : '.';
+  const contractFolderPath = getContractsFolderPath(path.join(rootPath, 'contracts'), contractName);
   await compileContract(contractFolderPath);
-  copyArtifacts(contractFolderPath, contractName);
+  await copyArtifacts(contractFolderPath, contractName, artifactName);
+  if (revision) await execPromise(`git worktree remove --force ${rootPath}`);
   console.log('Success!');
   process.exit(0);
 })(argvObj).catch((e) => {
@@ -1,6 +1,7 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #![feature(min_specialization)] //false positive - without this attribute contract does not compile
 
+/// Collateral token mock for tests, anyone can mint with mint_any_caller. It is redeployed rather than upgraded, so it does not implement Upgrading.
 #[brush::contract]
 pub mod psp22_emitable {
 
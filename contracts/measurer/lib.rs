@@ -5,11 +5,16 @@
 pub mod lending {
     use brush::contracts::ownable::*;
     use brush::contracts::pausable::*;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
     use ink_storage::traits::SpreadAllocate;
     use stable_coin_project::impls::measuring::*;
+    use stable_coin_project::impls::upgrading::*;
+
+    const STORAGE_VERSION: u32 = 1;
 
     #[ink(storage)]
-    #[derive(Default, SpreadAllocate, OwnableStorage, MeasuringStorage)]
+    #[derive(Default, SpreadAllocate, OwnableStorage, MeasuringStorage, UpgradingStorage)]
     pub struct MeasurerContract {
         #[OwnableStorageField]
         owner: OwnableData,
@@ -17,9 +22,12 @@ pub mod lending {
         // pause: PausableData,
         #[MeasuringStorageField]
         measure: MeasuringData,
+        #[UpgradingStorageField]
+        upgrading: UpgradingData,
     }
 
     impl Ownable for MeasurerContract {}
+    impl Upgrading for MeasurerContract {}
 
     impl Measuring for MeasurerContract {}
     impl MeasuringView for MeasurerContract {}
@@ -31,7 +39,39 @@ pub mod lending {
             ink_lang::codegen::initialize_contract(|instance: &mut MeasurerContract| {
                 instance.measure.oracle_address = oracle_address;
                 instance._init_with_owner(owner);
+                instance.upgrading.storage_version = STORAGE_VERSION;
             })
         }
     }
+
+    #[ink(event)]
+    pub struct Upgraded {
+        old_code_hash: Option<[u8; 32]>,
+        new_code_hash: [u8; 32],
+    }
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    impl UpgradingInternal for MeasurerContract {
+        fn _code_storage_version(&self) -> u32 {
+            STORAGE_VERSION
+        }
+
+        fn _emit_upgraded_event(&self, _old_code_hash: Option<[u8; 32]>, _new_code_hash: [u8; 32]) {
+            self.env().emit_event(Upgraded {
+                old_code_hash: _old_code_hash,
+                new_code_hash: _new_code_hash,
+            });
+        }
+
+        fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32) {
+            self.env().emit_event(Migrated {
+                from_version: _from_version,
+                to_version: _to_version,
+            });
+        }
+    }
 }
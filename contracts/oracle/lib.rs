@@ -1,6 +1,7 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #![feature(min_specialization)]
 
+/// Price feed mock for tests, anyone can feed prices. It is redeployed rather than upgraded, so it does not implement Upgrading.
 #[brush::contract]
 pub mod lending {
     use brush::contracts::ownable::*;
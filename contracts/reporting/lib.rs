@@ -0,0 +1,266 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// Read-only reporting of the system for dashboards.
+/// Aggregates debt and collateral of registered vaults, profit that was not distributed yet and breakdown of stable coin supply.
+/// Contract does not hold funds and has no roles on other contracts.
+#[brush::contract]
+pub mod reporting {
+    use brush::{contracts::ownable::*, contracts::psp22::*, modifiers};
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::SpreadAllocate;
+    use stable_coin_project::impls::upgrading::*;
+    use stable_coin_project::traits::collateralling::CollaterallingRef;
+    use stable_coin_project::traits::oracling::OraclingRef;
+    use stable_coin_project::traits::psp22_rated::PSP22RatedRef;
+    use stable_coin_project::traits::reporting::*;
+    use stable_coin_project::traits::shares_profit_controlling::PControllingRef;
+    use stable_coin_project::traits::shares_profit_generating::SPGeneratingRef;
+    use stable_coin_project::traits::vault::VaultRef;
+
+    const E6: u128 = 10_u128.pow(6);
+    const COLLATERAL_DECIMALS: u128 = 10_u128.pow(12);
+    const STORAGE_VERSION: u32 = 1;
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, OwnableStorage, UpgradingStorage)]
+    pub struct ReportingContract {
+        #[OwnableStorageField]
+        ownable: OwnableData,
+        #[UpgradingStorageField]
+        upgrading: UpgradingData,
+
+        // immutables
+        pub stable_coin_address: AccountId,
+        pub shares_profit_controller_address: AccountId,
+
+        // mutables_external
+        pub vaults: Vec<AccountId>,
+    }
+
+    impl ReportingContract {
+        #[ink(constructor)]
+        pub fn new(
+            stable_coin_address: AccountId,
+            shares_profit_controller_address: AccountId,
+            owner: AccountId,
+        ) -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut ReportingContract| {
+                instance._init_with_owner(owner);
+                instance.upgrading.storage_version = STORAGE_VERSION;
+                instance.stable_coin_address = stable_coin_address;
+                instance.shares_profit_controller_address = shares_profit_controller_address;
+            })
+        }
+    }
+
+    impl Ownable for ReportingContract {}
+    impl Upgrading for ReportingContract {}
+
+    impl Reporting for ReportingContract {
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn add_vault(&mut self, vault: AccountId) -> Result<(), ReportingError> {
+            if self.vaults.contains(&vault) {
+                return Err(ReportingError::VaultAlreadyAdded);
+            }
+            self.vaults.push(vault);
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn remove_vault(&mut self, vault: AccountId) -> Result<(), ReportingError> {
+            match self.vaults.iter().position(|added| *added == vault) {
+                Some(index) => {
+                    self.vaults.swap_remove(index);
+                    Ok(())
+                }
+                None => Err(ReportingError::VaultNotAdded),
+            }
+        }
+    }
+
+    impl ReportingView for ReportingContract {
+        #[ink(message)]
+        fn get_vaults(&self) -> Vec<AccountId> {
+            self.vaults.clone()
+        }
+
+        #[ink(message)]
+        fn get_stable_coin_address(&self) -> AccountId {
+            self.stable_coin_address
+        }
+
+        #[ink(message)]
+        fn get_shares_profit_controller_address(&self) -> AccountId {
+            self.shares_profit_controller_address
+        }
+
+        #[ink(message)]
+        fn get_system_report(&self, debt_holders: Vec<AccountId>) -> SystemReport {
+            let stable_coin_address = self.stable_coin_address;
+            let mut vaults = Vec::new();
+            let mut total_debt: Balance = 0;
+            let mut collateral_value: Balance = 0;
+            // profit is booked by generators on their updates, so it may lag behind current timestamp
+            let mut undistributed_profit: i128 =
+                PControllingRef::get_total_profit(&self.shares_profit_controller_address)
+                    + SPGeneratingRef::get_generated_profit(&stable_coin_address);
+            for vault in self.vaults.iter() {
+                let vault_report = self._vault_report(*vault);
+                total_debt = total_debt.saturating_add(vault_report.total_debt);
+                collateral_value = collateral_value.saturating_add(vault_report.collateral_value);
+                undistributed_profit += SPGeneratingRef::get_generated_profit(vault);
+                vaults.push(vault_report);
+            }
+
+            let treassury_address =
+                PControllingRef::get_treassury_address(&self.shares_profit_controller_address);
+            let mut debt_backed_supply: Balance = 0;
+            let mut counted: Vec<AccountId> = Vec::new();
+            for holder in debt_holders.iter() {
+                if counted.contains(holder) {
+                    continue;
+                }
+                counted.push(*holder);
+                debt_backed_supply += PSP22Ref::balance_of(&stable_coin_address, *holder)
+                    .min(PSP22RatedRef::account_debt(&stable_coin_address, *holder));
+            }
+
+            SystemReport {
+                vaults,
+                total_debt,
+                collateral_value,
+                collateral_ratio_e6: _collateral_ratio_e6(collateral_value, total_debt),
+                undistributed_profit,
+                treassury_holdings: PSP22Ref::balance_of(&stable_coin_address, treassury_address),
+                rated_supply: PSP22RatedRef::rated_supply(&stable_coin_address),
+                unrated_supply: PSP22RatedRef::unrated_supply(&stable_coin_address),
+                total_supply: PSP22Ref::total_supply(&stable_coin_address),
+                debt_backed_supply,
+            }
+        }
+    }
+
+    impl ReportingContract {
+        // collateral is valued with the oracle of the vault, the same way vault values it
+        // report must not trap on a huge price or amount, so the value saturates
+        fn _vault_report(&self, vault: AccountId) -> VaultReport {
+            // flash fees are held in collateral token too, but they are profit, they don't back any debt
+            let collateral_amount = CollaterallingRef::collateral_amount(&vault)
+                .saturating_sub(VaultRef::get_flash_fee_collateral(&vault));
+            let collateral_price_e6 =
+                OraclingRef::get_azero_usd_price_e6(&VaultRef::get_oracle_address(&vault));
+            VaultReport {
+                vault,
+                total_debt: VaultRef::get_total_debt(&vault),
+                collateral_amount,
+                collateral_price_e6,
+                collateral_value: collateral_amount.saturating_mul(collateral_price_e6)
+                    / COLLATERAL_DECIMALS,
+            }
+        }
+    }
+
+    // collateral_value / total_debt in e6, u128::MAX without debt
+    fn _collateral_ratio_e6(collateral_value: Balance, total_debt: Balance) -> u128 {
+        if total_debt == 0 {
+            return u128::MAX;
+        }
+        collateral_value.saturating_mul(E6) / total_debt
+    }
+
+    #[ink(event)]
+    pub struct Upgraded {
+        old_code_hash: Option<[u8; 32]>,
+        new_code_hash: [u8; 32],
+    }
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    impl UpgradingInternal for ReportingContract {
+        fn _code_storage_version(&self) -> u32 {
+            STORAGE_VERSION
+        }
+
+        fn _emit_upgraded_event(&self, _old_code_hash: Option<[u8; 32]>, _new_code_hash: [u8; 32]) {
+            self.env().emit_event(Upgraded {
+                old_code_hash: _old_code_hash,
+                new_code_hash: _new_code_hash,
+            });
+        }
+
+        fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32) {
+            self.env().emit_event(Migrated {
+                from_version: _from_version,
+                to_version: _to_version,
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use brush::test_utils::{accounts, change_caller};
+        use ink_lang as ink;
+
+        fn setup() -> ReportingContract {
+            let accounts = accounts();
+            change_caller(accounts.alice);
+            ReportingContract::new(accounts.bob, accounts.charlie, accounts.alice)
+        }
+
+        #[ink::test]
+        fn owner_registers_vaults() {
+            let accounts = accounts();
+            let mut instance = setup();
+            assert!(instance.add_vault(accounts.django).is_ok());
+            assert!(instance.add_vault(accounts.eve).is_ok());
+            assert_eq!(
+                instance.add_vault(accounts.django),
+                Err(ReportingError::VaultAlreadyAdded)
+            );
+            assert!(instance.remove_vault(accounts.django).is_ok());
+            assert_eq!(
+                instance.remove_vault(accounts.django),
+                Err(ReportingError::VaultNotAdded)
+            );
+            assert_eq!(instance.get_vaults(), vec![accounts.eve]);
+
+            change_caller(accounts.eve);
+            assert_eq!(
+                instance.add_vault(accounts.frank),
+                Err(ReportingError::OwnableError(OwnableError::CallerIsNotOwner))
+            );
+        }
+
+        #[ink::test]
+        fn first_call_after_upgrade_migrates() {
+            let accounts = accounts();
+            let mut instance = setup();
+            instance.upgrading.storage_version = STORAGE_VERSION - 1;
+            assert!(instance.add_vault(accounts.django).is_ok());
+            assert_eq!(instance.get_storage_version(), STORAGE_VERSION);
+
+            instance.upgrading.storage_version = STORAGE_VERSION + 1;
+            assert_eq!(
+                instance.migrate(),
+                Err(UpgradingError::StorageVersionAboveCode)
+            );
+        }
+
+        #[ink::test]
+        fn collateral_ratio_without_debt_is_max() {
+            assert_eq!(_collateral_ratio_e6(1_000, 0), u128::MAX);
+            assert_eq!(_collateral_ratio_e6(3_000, 2_000), 1_500_000);
+        }
+    }
+}
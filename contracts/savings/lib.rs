@@ -0,0 +1,407 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// Opt-in savings for stable coin holders (like DSR).
+/// Deposits are kept as pie, savings balance = pie * chi_e12 / E12. chi_e12 grows with savings_rate_e12.
+/// Contract should be unrated and tax free on stable coin, so deposits keep nominal value.
+#[brush::contract]
+pub mod savings {
+    use brush::{contracts::ownable::*, contracts::psp22::*, modifiers};
+    use ink_env::CallFlags;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::SpreadAllocate;
+    use ink_storage::Mapping;
+    use stable_coin_project::impls::upgrading::*;
+    use stable_coin_project::math::rpow_e12;
+    use stable_coin_project::traits::savings::*;
+
+    const E12: u128 = 10_u128.pow(12);
+    const STORAGE_VERSION: u32 = 1;
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, OwnableStorage, UpgradingStorage)]
+    pub struct SavingsContract {
+        #[OwnableStorageField]
+        ownable: OwnableData,
+        #[UpgradingStorageField]
+        upgrading: UpgradingData,
+
+        // immutables
+        pub stable_coin_address: AccountId,
+
+        // mutables_internal
+        pub chi_e12: u128, // accumulated savings rate, pie * chi_e12 / E12 = savings
+        pub last_drip_timestamp: Timestamp,
+        pub pie: Mapping<AccountId, Balance>,
+        pub total_pie: Balance,
+        pub unfunded_interest: Balance, // accrued interest that was not funded by shares profit controller yet
+
+        // mutables_external
+        pub savings_rate_e12: u128,
+        pub stable_controller_address: AccountId,
+        pub shares_profit_controller_address: AccountId,
+    }
+
+    impl SavingsContract {
+        #[ink(constructor)]
+        pub fn new(
+            stable_coin_address: AccountId,
+            stable_controller_address: AccountId,
+            shares_profit_controller_address: AccountId,
+            owner: AccountId,
+        ) -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut SavingsContract| {
+                instance._init_with_owner(owner);
+                instance.upgrading.storage_version = STORAGE_VERSION;
+                instance.stable_coin_address = stable_coin_address;
+                instance.stable_controller_address = stable_controller_address;
+                instance.shares_profit_controller_address = shares_profit_controller_address;
+                instance.chi_e12 = E12;
+                instance.last_drip_timestamp = instance.env().block_timestamp();
+            })
+        }
+    }
+
+    impl Ownable for SavingsContract {}
+    impl Upgrading for SavingsContract {}
+
+    impl Savings for SavingsContract {
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn deposit(&mut self, amount: Balance) -> Result<(), SavingsError> {
+            let caller = self.env().caller();
+            let contract = self.env().account_id();
+            let stable_coin_address = self.stable_coin_address;
+            self._drip();
+            // in case savings are not tax free only received amount is deposited
+            let balance_before = PSP22Ref::balance_of(&stable_coin_address, contract);
+            PSP22Ref::transfer_from_builder(
+                &stable_coin_address,
+                caller,
+                contract,
+                amount,
+                Vec::<u8>::new(),
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()?;
+            let received = PSP22Ref::balance_of(&stable_coin_address, contract) - balance_before;
+
+            let pie_amount = received * E12 / self.chi_e12;
+            self.pie
+                .insert(&caller, &(self._pie_of(&caller) + pie_amount));
+            self.total_pie += pie_amount;
+            self.env().emit_event(Deposit {
+                account: caller,
+                amount: received,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn withdraw(&mut self, amount: Balance) -> Result<(), SavingsError> {
+            let caller = self.env().caller();
+            let stable_coin_address = self.stable_coin_address;
+            self._drip();
+            // rounded up, so savings can't be withdrawn above savings balance
+            let pie_amount = (amount * E12 + self.chi_e12 - 1) / self.chi_e12;
+            let pie = self._pie_of(&caller);
+            if pie_amount > pie {
+                return Err(SavingsError::InsufficientSavings);
+            }
+            if PSP22Ref::balance_of(&stable_coin_address, self.env().account_id()) < amount {
+                return Err(SavingsError::NotFunded);
+            }
+            self.pie.insert(&caller, &(pie - pie_amount));
+            self.total_pie -= pie_amount;
+
+            PSP22Ref::transfer_builder(&stable_coin_address, caller, amount, Vec::<u8>::new())
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .fire()
+                .unwrap()?;
+            self.env().emit_event(Withdraw {
+                account: caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn drip(&mut self) -> Result<Balance, SavingsError> {
+            Ok(self._drip())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn set_savings_rate_e12(&mut self, new_savings_rate_e12: u128) -> Result<(), SavingsError> {
+            if self.env().caller() != self.stable_controller_address {
+                return Err(SavingsError::StableController);
+            }
+            self._drip();
+            self.savings_rate_e12 = new_savings_rate_e12;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn fund(&mut self, amount: Balance) -> Result<(), SavingsError> {
+            if self.env().caller() != self.shares_profit_controller_address {
+                return Err(SavingsError::SharesProfitController);
+            }
+            self._drip();
+            // funding above unfunded interest stays in savings as a buffer
+            self.unfunded_interest -= amount.min(self.unfunded_interest);
+            self.env().emit_event(Fund { amount });
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_stable_controller_address(
+            &mut self,
+            new_stable_controller_address: AccountId,
+        ) -> Result<(), SavingsError> {
+            self.stable_controller_address = new_stable_controller_address;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_shares_profit_controller_address(
+            &mut self,
+            new_shares_profit_controller_address: AccountId,
+        ) -> Result<(), SavingsError> {
+            self.shares_profit_controller_address = new_shares_profit_controller_address;
+            Ok(())
+        }
+    }
+
+    impl SavingsView for SavingsContract {
+        #[ink(message)]
+        fn savings_balance_of(&self, account: AccountId) -> Balance {
+            _savings_amount(self._pie_of(&account), self._get_chi_e12())
+        }
+
+        #[ink(message)]
+        fn total_savings(&self) -> Balance {
+            _savings_amount(self.total_pie, self._get_chi_e12())
+        }
+
+        #[ink(message)]
+        fn get_savings_rate_e12(&self) -> u128 {
+            self.savings_rate_e12
+        }
+
+        #[ink(message)]
+        fn get_chi_e12(&self) -> u128 {
+            self._get_chi_e12()
+        }
+
+        #[ink(message)]
+        fn get_last_drip_timestamp(&self) -> Timestamp {
+            self.last_drip_timestamp
+        }
+
+        #[ink(message)]
+        fn get_unfunded_interest(&self) -> Balance {
+            self.unfunded_interest + _savings_amount(self.total_pie, self._get_chi_e12())
+                - _savings_amount(self.total_pie, self.chi_e12)
+        }
+
+        #[ink(message)]
+        fn get_stable_coin_address(&self) -> AccountId {
+            self.stable_coin_address
+        }
+
+        #[ink(message)]
+        fn get_stable_controller_address(&self) -> AccountId {
+            self.stable_controller_address
+        }
+
+        #[ink(message)]
+        fn get_shares_profit_controller_address(&self) -> AccountId {
+            self.shares_profit_controller_address
+        }
+    }
+
+    impl PSP22Receiver for SavingsContract {
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            _value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22ReceiverError> {
+            Ok(())
+        }
+    }
+
+    impl SavingsContract {
+        fn _pie_of(&self, account: &AccountId) -> Balance {
+            self.pie.get(account).unwrap_or(0)
+        }
+
+        // chi * (1 + savings_rate)^dt
+        fn _get_chi_e12(&self) -> u128 {
+            let timestamp = self.env().block_timestamp();
+            if timestamp <= self.last_drip_timestamp {
+                return self.chi_e12;
+            }
+            self.chi_e12.saturating_mul(rpow_e12(
+                E12 + self.savings_rate_e12,
+                timestamp - self.last_drip_timestamp,
+            )) / E12
+        }
+
+        // updates chi, interest accrued to all deposits is owed until it is funded
+        fn _drip(&mut self) -> Balance {
+            let chi_e12 = self._get_chi_e12();
+            let accrued = _savings_amount(self.total_pie, chi_e12)
+                - _savings_amount(self.total_pie, self.chi_e12);
+            self.chi_e12 = chi_e12;
+            self.last_drip_timestamp = self.env().block_timestamp();
+            self.unfunded_interest += accrued;
+            accrued
+        }
+    }
+
+    // savings of pie_amount, rounded down
+    fn _savings_amount(pie_amount: Balance, chi_e12: u128) -> Balance {
+        pie_amount.saturating_mul(chi_e12) / E12
+    }
+
+    #[ink(event)]
+    pub struct Deposit {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdraw {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Fund {
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Upgraded {
+        old_code_hash: Option<[u8; 32]>,
+        new_code_hash: [u8; 32],
+    }
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    impl UpgradingInternal for SavingsContract {
+        fn _code_storage_version(&self) -> u32 {
+            STORAGE_VERSION
+        }
+
+        fn _emit_upgraded_event(&self, _old_code_hash: Option<[u8; 32]>, _new_code_hash: [u8; 32]) {
+            self.env().emit_event(Upgraded {
+                old_code_hash: _old_code_hash,
+                new_code_hash: _new_code_hash,
+            });
+        }
+
+        fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32) {
+            self.env().emit_event(Migrated {
+                from_version: _from_version,
+                to_version: _to_version,
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use brush::test_utils::{accounts, change_caller};
+        use ink_env::DefaultEnvironment;
+        use ink_lang as ink;
+
+        fn setup() -> SavingsContract {
+            let accounts = accounts();
+            change_caller(accounts.alice);
+            SavingsContract::new(
+                accounts.bob,
+                accounts.charlie,
+                accounts.django,
+                accounts.alice,
+            )
+        }
+
+        #[ink::test]
+        fn drip_accrues_unfunded_interest() {
+            let accounts = accounts();
+            let mut instance = setup();
+            instance.total_pie = 1_000_000_000_000;
+            instance.pie.insert(&accounts.eve, &1_000_000_000_000);
+            change_caller(accounts.charlie);
+            assert!(instance.set_savings_rate_e12(1_000_000).is_ok());
+            ink_env::test::advance_block::<DefaultEnvironment>();
+
+            let owed = instance.get_unfunded_interest();
+            assert!(owed > 0);
+            assert_eq!(instance.drip(), Ok(owed));
+            assert_eq!(instance.unfunded_interest, owed);
+            assert_eq!(
+                instance.savings_balance_of(accounts.eve),
+                1_000_000_000_000 + owed
+            );
+
+            change_caller(accounts.django);
+            assert!(instance.fund(owed + 1).is_ok());
+            assert_eq!(instance.get_unfunded_interest(), 0);
+        }
+
+        #[ink::test]
+        fn long_drip_saturates_chi() {
+            let accounts = accounts();
+            let mut instance = setup();
+            instance.total_pie = 1_000_000_000_000;
+            instance.pie.insert(&accounts.eve, &1_000_000_000_000);
+            change_caller(accounts.charlie);
+            // 100% per timestamp unit overflows chi within a few blocks
+            assert!(instance.set_savings_rate_e12(E12).is_ok());
+            let mut last_chi_e12 = instance.get_chi_e12();
+            for _ in 0..30 {
+                ink_env::test::advance_block::<DefaultEnvironment>();
+                assert!(instance.drip().is_ok());
+                assert!(instance.get_chi_e12() >= last_chi_e12);
+                last_chi_e12 = instance.get_chi_e12();
+            }
+            assert_eq!(last_chi_e12, u128::MAX / E12);
+            assert_eq!(instance.get_unfunded_interest(), instance.unfunded_interest);
+            assert!(instance.savings_balance_of(accounts.eve) > 1_000_000_000_000);
+        }
+
+        #[ink::test]
+        fn only_controllers_can_rate_and_fund() {
+            let accounts = accounts();
+            let mut instance = setup();
+            change_caller(accounts.django);
+            assert_eq!(
+                instance.set_savings_rate_e12(1),
+                Err(SavingsError::StableController)
+            );
+            change_caller(accounts.charlie);
+            assert_eq!(instance.fund(1), Err(SavingsError::SharesProfitController));
+        }
+    }
+}
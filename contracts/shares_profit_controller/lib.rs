@@ -4,25 +4,49 @@
 #[brush::contract]
 pub mod lending {
     use brush::contracts::ownable::*;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
     use ink_storage::traits::SpreadAllocate;
+    use ink_storage::Mapping;
     use stable_coin_project::impls::shares_profit_controlling::*;
+    use stable_coin_project::impls::upgrading::*;
+
+    // 1 - layout of the first upgradable controller
+    // 2 - savings address
+    const STORAGE_VERSION: u32 = 2;
 
     #[ink(storage)]
-    #[derive(Default, SpreadAllocate, OwnableStorage, SPControllingStorage)]
+    #[derive(Default, SpreadAllocate, OwnableStorage, SPControllingStorage, UpgradingStorage)]
     pub struct SPControllerContract {
         #[OwnableStorageField]
         owner: OwnableData,
         #[SPControllingStorageField]
         control: SPControllingData,
+        #[UpgradingStorageField]
+        upgrading: UpgradingData,
+
+        // fields appended after the first upgradable layout are kept in mappings, so loading storage of older versions,
+        // which has no entries for them, doesn't trap and missing entries read as defaults
+        // since storage version 2
+        savings_address: Mapping<(), AccountId>,
     }
 
     impl Ownable for SPControllerContract {}
+    impl Upgrading for SPControllerContract {}
 
     impl SPControlling for SPControllerContract {}
 
     impl SPControllingView for SPControllerContract {}
 
-    impl SPControllingInternal for SPControllerContract {}
+    impl SPControllingInternal for SPControllerContract {
+        fn _savings_address(&self) -> AccountId {
+            self.savings_address.get(&()).unwrap_or_default()
+        }
+
+        fn _set_savings_address(&mut self, savings_address: AccountId) {
+            self.savings_address.insert(&(), &savings_address);
+        }
+    }
 
     impl SPControllerContract {
         /// constructor with name and symbol
@@ -31,7 +55,44 @@ pub mod lending {
             ink_lang::codegen::initialize_contract(|instance: &mut SPControllerContract| {
                 instance.control.stable_coin_address = stable_coin_address;
                 instance._init_with_owner(owner);
+                instance.upgrading.storage_version = STORAGE_VERSION;
             })
         }
     }
+
+    #[ink(event)]
+    pub struct Upgraded {
+        old_code_hash: Option<[u8; 32]>,
+        new_code_hash: [u8; 32],
+    }
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    impl UpgradingInternal for SPControllerContract {
+        fn _code_storage_version(&self) -> u32 {
+            STORAGE_VERSION
+        }
+
+        fn _migrate(&mut self, _from_version: u32) -> Result<(), UpgradingError> {
+            // 2 - savings address is a mapping, its missing entry reads as zero address, so savings stay off until owner sets it
+            Ok(())
+        }
+
+        fn _emit_upgraded_event(&self, _old_code_hash: Option<[u8; 32]>, _new_code_hash: [u8; 32]) {
+            self.env().emit_event(Upgraded {
+                old_code_hash: _old_code_hash,
+                new_code_hash: _new_code_hash,
+            });
+        }
+
+        fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32) {
+            self.env().emit_event(Migrated {
+                from_version: _from_version,
+                to_version: _to_version,
+            });
+        }
+    }
 }
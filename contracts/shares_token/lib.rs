@@ -10,12 +10,15 @@ pub mod shares_token {
         contracts::psp22::extensions::mintable::*, modifiers,
     };
     use stable_coin_project::impls::pausing::*;
+    use stable_coin_project::impls::upgrading::*;
     use stable_coin_project::traits::managing::*;
+    use stable_coin_project::traits::snapshotting::*;
 
     use ink_lang::codegen::EmitEvent;
     use ink_lang::codegen::Env;
     use ink_prelude::string::String;
     use ink_storage::traits::SpreadAllocate;
+    use ink_storage::Mapping;
 
     const MINTER: RoleType = ink_lang::selector_id!("MINTER");
     const BURNER: RoleType = ink_lang::selector_id!("BURNER");
@@ -23,6 +26,7 @@ pub mod shares_token {
     const SHARES_DECIMALS: u128 = 10_u128.pow(6);
     const STABLE_DECIMALS: u128 = 10_u128.pow(6);
     const INIT_SUP: u128 = 10_u128.pow(7); //10 * 10^6
+    const STORAGE_VERSION: u32 = 1;
 
     #[ink(storage)]
     #[derive(
@@ -33,6 +37,7 @@ pub mod shares_token {
         PSP22Storage,
         PSP22MetadataStorage,
         AccessControlStorage,
+        UpgradingStorage,
     )]
     pub struct SharesContract {
         #[PSP22StorageField]
@@ -45,8 +50,16 @@ pub mod shares_token {
         pausable: PausableData,
         #[AccessControlStorageField]
         access: AccessControlData,
+        #[UpgradingStorageField]
+        upgrading: UpgradingData,
 
         pub total_minted_amount: Balance,
+
+        pub current_snapshot_id: u32,
+        pub account_checkpoint_count: Mapping<AccountId, u32>,
+        pub account_checkpoints: Mapping<(AccountId, u32), (u32, Balance)>, // (account, index) -> (snapshot_id, balance at snapshot_id)
+        pub supply_checkpoint_count: u32,
+        pub supply_checkpoints: Mapping<u32, (u32, Balance)>, // index -> (snapshot_id, total supply at snapshot_id)
     }
 
     impl SharesContract {
@@ -65,6 +78,7 @@ pub mod shares_token {
                 // ownable & access_control
                 instance._init_with_owner(owner);
                 instance._init_with_admin(owner);
+                instance.upgrading.storage_version = STORAGE_VERSION;
                 instance.total_minted_amount = INIT_SUP * SHARES_DECIMALS;
                 instance._mint(owner, INIT_SUP * SHARES_DECIMALS);
             })
@@ -72,6 +86,7 @@ pub mod shares_token {
     }
 
     impl Ownable for SharesContract {}
+    impl Upgrading for SharesContract {}
 
     impl OwnableInternal for SharesContract {
         fn _emit_ownership_transferred_event(
@@ -154,10 +169,131 @@ pub mod shares_token {
 
     impl PSP22 for SharesContract {}
 
+    impl PSP22Internal for SharesContract {
+        // checkpoints balances and supply before their first change after a snapshot
+        #[modifiers(when_migrated)]
+        fn _before_token_transfer(
+            &mut self,
+            from: Option<&AccountId>,
+            to: Option<&AccountId>,
+            _amount: &Balance,
+        ) -> Result<(), PSP22Error> {
+            if let Some(from) = from {
+                self._update_account_snapshot(*from);
+            }
+            if let Some(to) = to {
+                self._update_account_snapshot(*to);
+            }
+            if from.is_none() || to.is_none() {
+                self._update_supply_snapshot();
+            }
+            Ok(())
+        }
+    }
+
+    impl Snapshotting for SharesContract {
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn snapshot(&mut self) -> Result<u32, SnapshottingError> {
+            self.current_snapshot_id += 1;
+            self.env().emit_event(Snapshot {
+                id: self.current_snapshot_id,
+            });
+            Ok(self.current_snapshot_id)
+        }
+
+        #[ink(message)]
+        fn balance_of_at(
+            &self,
+            account: AccountId,
+            snapshot_id: u32,
+        ) -> Result<Balance, SnapshottingError> {
+            self._check_snapshot_id(snapshot_id)?;
+            let count = self.account_checkpoint_count.get(&account).unwrap_or(0);
+            match find_checkpoint(count, snapshot_id, |index| {
+                self.account_checkpoints
+                    .get(&(account, index))
+                    .unwrap_or_default()
+                    .0
+            }) {
+                Some(index) => Ok(self
+                    .account_checkpoints
+                    .get(&(account, index))
+                    .unwrap_or_default()
+                    .1),
+                None => Ok(self._balance_of(&account)),
+            }
+        }
+
+        #[ink(message)]
+        fn total_supply_at(&self, snapshot_id: u32) -> Result<Balance, SnapshottingError> {
+            self._check_snapshot_id(snapshot_id)?;
+            match find_checkpoint(self.supply_checkpoint_count, snapshot_id, |index| {
+                self.supply_checkpoints.get(&index).unwrap_or_default().0
+            }) {
+                Some(index) => Ok(self.supply_checkpoints.get(&index).unwrap_or_default().1),
+                None => Ok(self.psp22.supply),
+            }
+        }
+
+        #[ink(message)]
+        fn current_snapshot_id(&self) -> u32 {
+            self.current_snapshot_id
+        }
+    }
+
+    impl SharesContract {
+        fn _check_snapshot_id(&self, snapshot_id: u32) -> Result<(), SnapshottingError> {
+            if snapshot_id == 0 || snapshot_id > self.current_snapshot_id {
+                return Err(SnapshottingError::NonexistentSnapshot);
+            }
+            Ok(())
+        }
+
+        // stores balance as it was at the current snapshot if it was not stored yet
+        fn _update_account_snapshot(&mut self, account: AccountId) {
+            let count = self.account_checkpoint_count.get(&account).unwrap_or(0);
+            let last_id = if count == 0 {
+                0
+            } else {
+                self.account_checkpoints
+                    .get(&(account, count - 1))
+                    .unwrap_or_default()
+                    .0
+            };
+            if last_id < self.current_snapshot_id {
+                self.account_checkpoints.insert(
+                    &(account, count),
+                    &(self.current_snapshot_id, self._balance_of(&account)),
+                );
+                self.account_checkpoint_count.insert(&account, &(count + 1));
+            }
+        }
+
+        fn _update_supply_snapshot(&mut self) {
+            let count = self.supply_checkpoint_count;
+            let last_id = if count == 0 {
+                0
+            } else {
+                self.supply_checkpoints
+                    .get(&(count - 1))
+                    .unwrap_or_default()
+                    .0
+            };
+            if last_id < self.current_snapshot_id {
+                self.supply_checkpoints
+                    .insert(&count, &(self.current_snapshot_id, self.psp22.supply));
+                self.supply_checkpoint_count = count + 1;
+            }
+        }
+    }
+
     impl PSP22Metadata for SharesContract {}
 
     impl PSP22Mintable for SharesContract {
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_role(MINTER))]
         #[modifiers(when_not_paused)]
         fn mint(&mut self, account: AccountId, amount: Balance) -> Result<(), PSP22Error> {
@@ -170,6 +306,7 @@ pub mod shares_token {
 
     impl PSP22Burnable for SharesContract {
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_role(BURNER))]
         fn burn(&mut self, account: AccountId, amount: Balance) -> Result<(), PSP22Error> {
             self._burn_from(account, amount)?;
@@ -178,6 +315,11 @@ pub mod shares_token {
     }
 
     // EVENT DEFINITIONS #[ink(event)]
+    #[ink(event)]
+    pub struct Snapshot {
+        id: u32,
+    }
+
     #[ink(event)]
     pub struct OwnershipTransferred {
         #[ink(topic)]
@@ -215,4 +357,35 @@ pub mod shares_token {
         #[ink(topic)]
         admin: AccountId,
     }
+
+    #[ink(event)]
+    pub struct Upgraded {
+        old_code_hash: Option<[u8; 32]>,
+        new_code_hash: [u8; 32],
+    }
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    impl UpgradingInternal for SharesContract {
+        fn _code_storage_version(&self) -> u32 {
+            STORAGE_VERSION
+        }
+
+        fn _emit_upgraded_event(&self, _old_code_hash: Option<[u8; 32]>, _new_code_hash: [u8; 32]) {
+            self.env().emit_event(Upgraded {
+                old_code_hash: _old_code_hash,
+                new_code_hash: _new_code_hash,
+            });
+        }
+
+        fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32) {
+            self.env().emit_event(Migrated {
+                from_version: _from_version,
+                to_version: _to_version,
+            });
+        }
+    }
 }
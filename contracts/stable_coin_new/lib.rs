@@ -11,14 +11,24 @@ pub mod stable_coin {
         contracts::psp22::extensions::burnable::*,
         contracts::psp22::extensions::metadata::*,
         contracts::psp22::extensions::mintable::*,
+        contracts::reentrancy_guard::*,
         modifiers,
         traits::{AccountIdExt, Flush},
     };
     use stable_coin_project::impls::pausing::*;
     use stable_coin_project::impls::shares_profit_generating::*;
+    use stable_coin_project::impls::upgrading::*;
+    use stable_coin_project::math::rpow_e12;
+    use stable_coin_project::traits::flash_lending::*;
+    use stable_coin_project::traits::freezing::*;
     use stable_coin_project::traits::managing::*;
+    use stable_coin_project::traits::mint_limiting::*;
+    use stable_coin_project::traits::psp22_batch::*;
+    use stable_coin_project::traits::psp22_permit::*;
     use stable_coin_project::traits::psp22_rated::*;
+    use stable_coin_project::traits::snapshotting::*;
 
+    use ink_env::hash::{Blake2x256, HashOutput};
     use ink_env::{CallFlags, Error as EnvError};
     use ink_lang::codegen::EmitEvent;
     use ink_lang::codegen::Env;
@@ -26,6 +36,7 @@ pub mod stable_coin {
 
     use ink_storage::traits::SpreadAllocate;
     use ink_storage::Mapping;
+    use scale::Encode;
 
     const E6: u128 = 10_u128.pow(6);
     const E12: u128 = 10_u128.pow(12);
@@ -35,6 +46,11 @@ pub mod stable_coin {
     // const EMITTER: RoleType = ink_lang::selector_id!("EMITTER");
     // const SETTER: RoleType = ink_lang::selector_id!("SETTER"); // for now we use owner
     const VAULT: RoleType = ink_lang::selector_id!("VAULT");
+    const FREEZER: RoleType = ink_lang::selector_id!("FREEZER");
+    const PERMIT_DOMAIN: &[u8] = b"StableCoinPermit";
+    // 1 - nominal balances with denominator applied per account
+    // 2 - scaled balances of rated accounts
+    const STORAGE_VERSION: u32 = 2;
 
     #[ink(storage)]
     #[derive(
@@ -46,6 +62,8 @@ pub mod stable_coin {
         PSP22MetadataStorage,
         AccessControlStorage,
         SPGeneratingStorage,
+        ReentrancyGuardStorage,
+        UpgradingStorage,
     )]
     pub struct StableCoinContract {
         #[OwnableStorageField]
@@ -60,15 +78,18 @@ pub mod stable_coin {
         metadata: PSP22MetadataData,
         #[SPGeneratingStorageField]
         spgenerate: SPGeneratingData,
+        #[ReentrancyGuardStorageField]
+        guard: ReentrancyGuardData,
+        #[UpgradingStorageField]
+        upgrading: UpgradingData,
 
         // immutables
 
         // mutables_internal
-        pub rated_supply: Balance,
+        pub scaled_rated_supply: Balance, // sum of scaled balances of rated accounts
         pub unrated_supply: Balance,
-        pub current_denominator_e12: u128,
+        pub current_denominator_e12: u128, // rated balance = scaled balance * E12 / denominator
         pub last_current_denominator_update_timestamp: Timestamp,
-        pub applied_denominator_e12: Mapping<AccountId, u128>,
 
         // mutables_external
         pub is_unrated: Mapping<AccountId, bool>,
@@ -79,6 +100,30 @@ pub mod stable_coin {
         pub tax_e6: u128,
 
         pub account_debt: Mapping<AccountId, Balance>, //TODO think about moving this mapping to different contracts
+
+        pub max_flash_loan: Balance,
+        pub flash_fee_e6: u128,
+
+        pub nonces: Mapping<AccountId, u64>, // nonce of the next permit of owner
+
+        pub is_frozen: Mapping<AccountId, bool>, // frozen accounts can't send, receive or be minted to
+
+        pub current_snapshot_id: u32,
+        pub denominator_at_snapshot_e12: Mapping<u32, u128>,
+        pub account_checkpoint_count: Mapping<AccountId, u32>,
+        pub account_checkpoints: Mapping<(AccountId, u32), (u32, Balance, bool)>, // (account, index) -> (snapshot_id, raw balance, is_unrated)
+        pub supply_checkpoint_count: u32,
+        pub supply_checkpoints: Mapping<u32, (u32, Balance, Balance)>, // index -> (snapshot_id, scaled rated supply, unrated supply)
+
+        // single values are kept in mappings, so when fields are appended in a later version, storage of this version,
+        // which has no entries for them, can still be loaded and missing entries read as defaults
+        pub tax_policy: Mapping<(), TaxPolicy>,
+
+        pub minter_quotas: Mapping<AccountId, Option<MinterQuota>>,
+        pub minter_outstanding: Mapping<AccountId, Balance>,
+        pub minter_window_usage: Mapping<AccountId, (Balance, Timestamp)>, // (used capacity, timestamp of last mint)
+
+        pub chain_id: Mapping<(), [u8; 32]>, // genesis hash of the chain, permits are refused until it is set
     }
 
     impl StableCoinContract {
@@ -98,6 +143,7 @@ pub mod stable_coin {
                 // ownable
                 instance._init_with_owner(owner);
                 instance._init_with_admin(owner);
+                instance.upgrading.storage_version = STORAGE_VERSION;
                 // TaxedCoinData
                 instance.spgenerate.shares_token_address = shares_token_address;
                 instance.spgenerate.sharing_part_e6 = E6;
@@ -111,6 +157,7 @@ pub mod stable_coin {
     }
 
     impl Ownable for StableCoinContract {}
+    impl Upgrading for StableCoinContract {}
 
     impl OwnableInternal for StableCoinContract {
         fn _emit_ownership_transferred_event(
@@ -189,18 +236,69 @@ pub mod stable_coin {
 
     impl PSP22Mintable for StableCoinContract {
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_role(MINTER))]
         #[modifiers(when_not_paused)]
         fn mint(&mut self, account: AccountId, amount: Balance) -> Result<(), PSP22Error> {
-            self._mint(account, amount)
+            let minter = self.env().caller();
+            self._check_mint_capacity(minter, amount)?;
+            self._mint(account, amount)?;
+            self._use_mint_capacity(minter, amount);
+            // minters without quota have unlimited capacity, so it is emitted only for limited ones
+            if self._remaining_mint_capacity(&minter) == 0 {
+                self.env().emit_event(MintCapacityExhausted {
+                    minter,
+                    outstanding: self._minter_outstanding(&minter),
+                });
+            }
+            Ok(())
         }
     }
 
     impl PSP22Burnable for StableCoinContract {
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_role(BURNER))]
         fn burn(&mut self, account: AccountId, amount: Balance) -> Result<(), PSP22Error> {
-            self._burn_from(account, amount)
+            self._burn_from(account, amount)?;
+            // burning by minter (like vault on repay) frees its outstanding amount
+            let burner = self.env().caller();
+            let outstanding = self._minter_outstanding(&burner);
+            self.minter_outstanding
+                .insert(&burner, &outstanding.saturating_sub(amount));
+            Ok(())
+        }
+    }
+
+    impl MintLimiting for StableCoinContract {
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_minter_quota(
+            &mut self,
+            minter: AccountId,
+            quota: Option<MinterQuota>,
+        ) -> Result<(), MintLimitError> {
+            self.minter_quotas.insert(&minter, &quota);
+            self.env().emit_event(MinterQuotaSet { minter, quota });
+            Ok(())
+        }
+    }
+
+    impl MintLimitingView for StableCoinContract {
+        #[ink(message)]
+        fn minter_quota(&self, minter: AccountId) -> Option<MinterQuota> {
+            self._minter_quota(&minter)
+        }
+
+        #[ink(message)]
+        fn minter_outstanding(&self, minter: AccountId) -> Balance {
+            self._minter_outstanding(&minter)
+        }
+
+        #[ink(message)]
+        fn remaining_mint_capacity(&self, minter: AccountId) -> Balance {
+            self._remaining_mint_capacity(&minter)
         }
     }
 
@@ -227,25 +325,11 @@ pub mod stable_coin {
             })
         }
         fn _balance_of(&self, owner: &AccountId) -> Balance {
-            let unupdated_balance = self._unupdated_balance_of(owner);
+            let raw_balance = self._raw_balance_of(owner);
             if self._is_unrated(owner) {
-                return unupdated_balance;
-            }
-            let applied_denominator_e12 = self._applied_denominator_e12(owner);
-            let current_denominator_e12 = self.current_denominator_e12;
-            if current_denominator_e12 > applied_denominator_e12 {
-                let denominator_difference_e12 = current_denominator_e12 - applied_denominator_e12;
-                let to_add =
-                    unupdated_balance * denominator_difference_e12 / current_denominator_e12;
-                return unupdated_balance + to_add;
-            } else if current_denominator_e12 < applied_denominator_e12 {
-                let denominator_difference_e12 = applied_denominator_e12 - current_denominator_e12;
-                let to_sub =
-                    unupdated_balance * denominator_difference_e12 / current_denominator_e12;
-                return unupdated_balance - to_sub;
-            } else {
-                return unupdated_balance;
+                return raw_balance;
             }
+            _rated_amount(raw_balance, self._get_current_denominator_e12())
         }
 
         fn _do_safe_transfer_check(
@@ -287,6 +371,7 @@ pub mod stable_coin {
             Ok(())
         }
 
+        #[modifiers(when_migrated)]
         fn _transfer_from_to(
             &mut self,
             from: AccountId,
@@ -301,24 +386,18 @@ pub mod stable_coin {
             if to.is_zero() {
                 return Err(PSP22Error::ZeroRecipientAddress);
             }
+            if self._is_frozen(&from) || self._is_frozen(&to) {
+                return Err(PSP22Error::Custom(String::from("AccountFrozen")));
+            }
             // self._before_token_transfer(Some(&account), None, &amount)?;
             self._do_safe_transfer_check(&from, &to, &amount, &data)?;
 
-            let current_denominator_e12 = self._update_current_denominator_e12();
-            self._decrease_balance(from, amount, current_denominator_e12)?;
-            let tax_e6 = self.tax_e6;
-            if tax_e6 == 0 {
-                self._increase_balance(to, amount, current_denominator_e12);
-            } else {
-                let tax = self._calculate_tax(to, amount, tax_e6);
-                self._increase_balance(to, amount - tax, current_denominator_e12);
-                self._add_profit_and_increase_shares_minting_allowance(tax, to);
-                // this comes from tax
-            }
+            self._transfer_balance(from, to, amount)?;
             // self._after_token_transfer(Some(&account), None, &amount)?;
             self._emit_transfer_event(Some(from), Some(to), amount);
             Ok(())
         }
+        #[modifiers(when_migrated)]
         fn _approve_from_to(
             &mut self,
             owner: AccountId,
@@ -341,11 +420,13 @@ pub mod stable_coin {
             if account.is_zero() {
                 return Err(PSP22Error::ZeroRecipientAddress);
             }
+            if self._is_frozen(&account) {
+                return Err(PSP22Error::Custom(String::from("AccountFrozen")));
+            }
             // self._before_token_transfer(Some(&account), None, &amount)?;
 
             let current_denominator_e12 = self._update_current_denominator_e12();
             self._increase_balance(account, amount, current_denominator_e12);
-            self.psp22.supply += amount;
 
             // self._after_token_transfer(Some(&account), None, &amount)?;
             self._emit_transfer_event(None, Some(account), amount);
@@ -360,7 +441,6 @@ pub mod stable_coin {
 
             let current_denominator_e12 = self._update_current_denominator_e12();
             self._decrease_balance(account, amount, current_denominator_e12)?;
-            self.psp22.supply -= amount;
 
             // self._after_token_transfer(Some(&account), None, &amount)?;
             self._emit_transfer_event(Some(account), None, amount);
@@ -369,15 +449,634 @@ pub mod stable_coin {
         }
     }
 
-    impl PSP22 for StableCoinContract {}
+    impl PSP22 for StableCoinContract {
+        // balances of rated accounts change with time, so supply is derived from scaled supply
+        #[ink(message)]
+        fn total_supply(&self) -> Balance {
+            self.rated_supply() + self.unrated_supply
+        }
+    }
+
+    impl FlashLending for StableCoinContract {
+        // mints amount to receiver, calls it back and burns amount + fee, fee is added to profit
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(when_not_paused)]
+        #[modifiers(non_reentrant)]
+        fn flash_loan(
+            &mut self,
+            receiver: AccountId,
+            amount: Balance,
+            data: Vec<u8>,
+        ) -> Result<(), FlashLendingError> {
+            if amount > self.max_flash_loan {
+                return Err(FlashLendingError::AmountAboveMaximum);
+            }
+            let initiator = self.env().caller();
+            let lender = self.env().account_id();
+            let fee = self._flash_fee(amount);
+            // flash mint does not use minter quotas on purpose, amount is burned back in the same call
+            // and anyone can flash mint, so there is no minter the quota could be charged to
+            self._mint(receiver, amount)?;
+
+            // receiver has to approve lender to take back amount + fee
+            self.flush();
+            let result = FlashBorrowerRef::on_flash_loan_builder(
+                &receiver, initiator, lender, amount, fee, data,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire();
+            self.load();
+            match result {
+                Ok(result) => result?,
+                Err(_) => return Err(FlashLendingError::CallbackFailed),
+            }
+
+            self._settle_flash_loan(receiver, amount, fee)?;
+
+            self.env().emit_event(FlashLoan {
+                initiator,
+                receiver,
+                amount,
+                fee,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_max_flash_loan(
+            &mut self,
+            new_max_flash_loan: Balance,
+        ) -> Result<(), FlashLendingError> {
+            self.max_flash_loan = new_max_flash_loan;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_flash_fee_e6(&mut self, new_flash_fee_e6: u128) -> Result<(), FlashLendingError> {
+            if new_flash_fee_e6 > E6 {
+                return Err(FlashLendingError::FeeAboveOne);
+            }
+            self.flash_fee_e6 = new_flash_fee_e6;
+            Ok(())
+        }
+    }
+
+    impl FlashLendingView for StableCoinContract {
+        #[ink(message)]
+        fn max_flash_loan(&self) -> Balance {
+            self.max_flash_loan
+        }
+
+        #[ink(message)]
+        fn flash_fee(&self, amount: Balance) -> Balance {
+            self._flash_fee(amount)
+        }
+
+        #[ink(message)]
+        fn flash_fee_e6(&self) -> u128 {
+            self.flash_fee_e6
+        }
+    }
+
+    impl Freezing for StableCoinContract {
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_role(FREEZER))]
+        fn freeze(&mut self, account: AccountId) -> Result<(), FreezingError> {
+            self.is_frozen.insert(&account, &true);
+            self.env().emit_event(Frozen {
+                account,
+                by: self.env().caller(),
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_role(FREEZER))]
+        fn unfreeze(&mut self, account: AccountId) -> Result<(), FreezingError> {
+            self.is_frozen.insert(&account, &false);
+            self.env().emit_event(Unfrozen {
+                account,
+                by: self.env().caller(),
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn wipe_frozen(&mut self, account: AccountId) -> Result<Balance, FreezingError> {
+            if !self._is_frozen(&account) {
+                return Err(FreezingError::NotFrozen);
+            }
+            self._update_current_denominator_e12();
+            let amount = self._balance_of(&account);
+            self._burn_from(account, amount)?;
+            self.env().emit_event(FrozenWiped {
+                account,
+                amount,
+                by: self.env().caller(),
+            });
+            Ok(amount)
+        }
+    }
+
+    impl FreezingView for StableCoinContract {
+        #[ink(message)]
+        fn is_frozen(&self, account: AccountId) -> bool {
+            self._is_frozen(&account)
+        }
+    }
+
+    impl Snapshotting for StableCoinContract {
+        // denominator in force is stored, so rated balances at snapshot can be calculated later
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn snapshot(&mut self) -> Result<u32, SnapshottingError> {
+            let current_denominator_e12 = self._update_current_denominator_e12();
+            self.current_snapshot_id += 1;
+            self.denominator_at_snapshot_e12
+                .insert(&self.current_snapshot_id, &current_denominator_e12);
+            self.env().emit_event(Snapshot {
+                id: self.current_snapshot_id,
+            });
+            Ok(self.current_snapshot_id)
+        }
+
+        #[ink(message)]
+        fn balance_of_at(
+            &self,
+            account: AccountId,
+            snapshot_id: u32,
+        ) -> Result<Balance, SnapshottingError> {
+            self._check_snapshot_id(snapshot_id)?;
+            let count = self.account_checkpoint_count.get(&account).unwrap_or(0);
+            let (raw_balance, is_unrated) = match find_checkpoint(count, snapshot_id, |index| {
+                self.account_checkpoints
+                    .get(&(account, index))
+                    .unwrap_or_default()
+                    .0
+            }) {
+                Some(index) => {
+                    let checkpoint = self
+                        .account_checkpoints
+                        .get(&(account, index))
+                        .unwrap_or_default();
+                    (checkpoint.1, checkpoint.2)
+                }
+                // account has not changed since the snapshot
+                None => (self._raw_balance_of(&account), self._is_unrated(&account)),
+            };
+            if is_unrated {
+                return Ok(raw_balance);
+            }
+            Ok(_rated_amount(
+                raw_balance,
+                self._denominator_at_snapshot_e12(snapshot_id),
+            ))
+        }
+
+        #[ink(message)]
+        fn total_supply_at(&self, snapshot_id: u32) -> Result<Balance, SnapshottingError> {
+            self._check_snapshot_id(snapshot_id)?;
+            let (scaled_rated_supply, unrated_supply) =
+                match find_checkpoint(self.supply_checkpoint_count, snapshot_id, |index| {
+                    self.supply_checkpoints.get(&index).unwrap_or_default().0
+                }) {
+                    Some(index) => {
+                        let checkpoint = self.supply_checkpoints.get(&index).unwrap_or_default();
+                        (checkpoint.1, checkpoint.2)
+                    }
+                    None => (self.scaled_rated_supply, self.unrated_supply),
+                };
+            Ok(_rated_amount(
+                scaled_rated_supply,
+                self._denominator_at_snapshot_e12(snapshot_id),
+            ) + unrated_supply)
+        }
+
+        #[ink(message)]
+        fn current_snapshot_id(&self) -> u32 {
+            self.current_snapshot_id
+        }
+    }
+
+    impl PSP22Batch for StableCoinContract {
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn transfer_batch(
+            &mut self,
+            transfers: Vec<(AccountId, Balance)>,
+            data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let from = self.env().caller();
+            if self._is_frozen(&from) {
+                return Err(PSP22Error::Custom(String::from("AccountFrozen")));
+            }
+            for (to, amount) in transfers.iter() {
+                if to.is_zero() {
+                    return Err(PSP22Error::ZeroRecipientAddress);
+                }
+                if self._is_frozen(to) {
+                    return Err(PSP22Error::Custom(String::from("AccountFrozen")));
+                }
+                self._do_safe_transfer_check(&from, to, amount, &data)?;
+            }
+            self._transfer_batch_balance(from, &transfers)
+        }
+    }
+
+    impl PSP22Permit for StableCoinContract {
+        // approves spender to spend value of owners tokens if owner signed it for the current nonce
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn permit(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<(), PermitError> {
+            if self.env().block_timestamp() > deadline {
+                return Err(PermitError::Expired);
+            }
+            if self.chain_id.get(&()).is_none() {
+                return Err(PermitError::ChainIdNotSet);
+            }
+            let nonce = self.nonces.get(&owner).unwrap_or(0);
+            let message_hash = self._permit_hash(owner, spender, value, nonce, deadline);
+            let mut public_key = [0_u8; 33];
+            ink_env::ecdsa_recover(&signature, &message_hash, &mut public_key)
+                .map_err(|_| PermitError::InvalidSignature)?;
+            if AccountId::from(_blake2x256(&public_key)) != owner {
+                return Err(PermitError::InvalidSignature);
+            }
+            self.nonces.insert(&owner, &(nonce + 1));
+            self._approve_from_to(owner, spender, value)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn nonces(&self, owner: AccountId) -> u64 {
+            self.nonces.get(&owner).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        fn domain_separator(&self) -> [u8; 32] {
+            self._domain_separator()
+        }
+
+        // contract ink! can't read chain id, so owner sets genesis hash of the chain
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_chain_id(&mut self, chain_id: [u8; 32]) -> Result<(), PSP22Error> {
+            self.chain_id.insert(&(), &chain_id);
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn chain_id(&self) -> Option<[u8; 32]> {
+            self.chain_id.get(&())
+        }
+    }
+
+    impl StableCoinContract {
+        fn _minter_quota(&self, minter: &AccountId) -> Option<MinterQuota> {
+            self.minter_quotas.get(minter).unwrap_or(None)
+        }
+
+        fn _minter_outstanding(&self, minter: &AccountId) -> Balance {
+            self.minter_outstanding.get(minter).unwrap_or(0)
+        }
+
+        // window capacity used by minter, released linearly with time
+        fn _minter_window_usage(&self, minter: &AccountId, quota: &MinterQuota) -> Balance {
+            if quota.window == 0 {
+                return 0;
+            }
+            let (used, last_timestamp) = self.minter_window_usage.get(minter).unwrap_or((0, 0));
+            let time_passed = self.env().block_timestamp().saturating_sub(last_timestamp);
+            let released =
+                quota.window_limit * time_passed.min(quota.window) as u128 / quota.window as u128;
+            used.saturating_sub(released)
+        }
+
+        fn _remaining_mint_capacity(&self, minter: &AccountId) -> Balance {
+            let quota = match self._minter_quota(minter) {
+                Some(quota) => quota,
+                None => return Balance::MAX,
+            };
+            let outstanding_capacity = quota
+                .max_outstanding
+                .saturating_sub(self._minter_outstanding(minter));
+            if quota.window == 0 {
+                return outstanding_capacity;
+            }
+            let window_capacity = quota
+                .window_limit
+                .saturating_sub(self._minter_window_usage(minter, &quota));
+            outstanding_capacity.min(window_capacity)
+        }
+
+        fn _check_mint_capacity(
+            &self,
+            minter: AccountId,
+            amount: Balance,
+        ) -> Result<(), MintLimitError> {
+            if amount > self._remaining_mint_capacity(&minter) {
+                return Err(MintLimitError::QuotaExceeded);
+            }
+            Ok(())
+        }
+
+        // outstanding is tracked also for minters without quota, so a later quota sees real state
+        fn _use_mint_capacity(&mut self, minter: AccountId, amount: Balance) {
+            self.minter_outstanding
+                .insert(&minter, &(self._minter_outstanding(&minter) + amount));
+            if let Some(quota) = self._minter_quota(&minter) {
+                if quota.window != 0 {
+                    let used = self._minter_window_usage(&minter, &quota);
+                    self.minter_window_usage
+                        .insert(&minter, &(used + amount, self.env().block_timestamp()));
+                }
+            }
+        }
+
+        fn _check_snapshot_id(&self, snapshot_id: u32) -> Result<(), SnapshottingError> {
+            if snapshot_id == 0 || snapshot_id > self.current_snapshot_id {
+                return Err(SnapshottingError::NonexistentSnapshot);
+            }
+            Ok(())
+        }
+
+        // stores raw balance state as it was at the current snapshot if it was not stored yet
+        fn _update_account_snapshot(&mut self, account: AccountId) {
+            let count = self.account_checkpoint_count.get(&account).unwrap_or(0);
+            let last_id = if count == 0 {
+                0
+            } else {
+                self.account_checkpoints
+                    .get(&(account, count - 1))
+                    .unwrap_or_default()
+                    .0
+            };
+            if last_id < self.current_snapshot_id {
+                self.account_checkpoints.insert(
+                    &(account, count),
+                    &(
+                        self.current_snapshot_id,
+                        self._raw_balance_of(&account),
+                        self._is_unrated(&account),
+                    ),
+                );
+                self.account_checkpoint_count.insert(&account, &(count + 1));
+            }
+        }
+
+        fn _update_supply_snapshot(&mut self) {
+            let count = self.supply_checkpoint_count;
+            let last_id = if count == 0 {
+                0
+            } else {
+                self.supply_checkpoints
+                    .get(&(count - 1))
+                    .unwrap_or_default()
+                    .0
+            };
+            if last_id < self.current_snapshot_id {
+                self.supply_checkpoints.insert(
+                    &count,
+                    &(
+                        self.current_snapshot_id,
+                        self.scaled_rated_supply,
+                        self.unrated_supply,
+                    ),
+                );
+                self.supply_checkpoint_count = count + 1;
+            }
+        }
+
+        fn _denominator_at_snapshot_e12(&self, snapshot_id: u32) -> u128 {
+            self.denominator_at_snapshot_e12
+                .get(&snapshot_id)
+                .unwrap_or(E12)
+        }
+
+        // moves amount between accounts, tax is taken from the received amount
+        fn _transfer_balance(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            amount: Balance,
+        ) -> Result<(), PSP22Error> {
+            let current_denominator_e12 = self._update_current_denominator_e12();
+            let (sender_tax, recipient_tax) = self._calculate_transfer_taxes(from, to, amount);
+            self._decrease_balance(from, amount, current_denominator_e12)?;
+            self._increase_balance(
+                to,
+                amount - sender_tax - recipient_tax,
+                current_denominator_e12,
+            );
+            self._distribute_tax(sender_tax, from, current_denominator_e12);
+            self._distribute_tax(recipient_tax, to, current_denominator_e12);
+            Ok(())
+        }
+
+        // decreases balance of from once by the sum, nothing changes if the sum is not covered
+        fn _transfer_batch_balance(
+            &mut self,
+            from: AccountId,
+            transfers: &[(AccountId, Balance)],
+        ) -> Result<(), PSP22Error> {
+            let mut total: Balance = 0;
+            for (_, amount) in transfers.iter() {
+                total = total
+                    .checked_add(*amount)
+                    .ok_or(PSP22Error::InsufficientBalance)?;
+            }
+            if total > self._balance_of(&from) {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+            let current_denominator_e12 = self._update_current_denominator_e12();
+            // taxes are calculated before balances change, like in a single transfer
+            let taxes: Vec<(Balance, Balance)> = transfers
+                .iter()
+                .map(|(to, amount)| self._calculate_transfer_taxes(from, *to, *amount))
+                .collect();
+            self._decrease_balance(from, total, current_denominator_e12)?;
+            for ((to, amount), (sender_tax, recipient_tax)) in transfers.iter().zip(taxes) {
+                self._increase_balance(
+                    *to,
+                    amount - sender_tax - recipient_tax,
+                    current_denominator_e12,
+                );
+                self._distribute_tax(sender_tax, from, current_denominator_e12);
+                self._distribute_tax(recipient_tax, *to, current_denominator_e12);
+                self._emit_transfer_event(Some(from), Some(*to), *amount);
+            }
+            Ok(())
+        }
+
+        // taxes of both parties according to tax policy, together at most amount
+        fn _calculate_transfer_taxes(
+            &self,
+            from: AccountId,
+            to: AccountId,
+            amount: Balance,
+        ) -> (Balance, Balance) {
+            let tax_e6 = self.tax_e6;
+            if tax_e6 == 0 {
+                return (0, 0);
+            }
+            let taxed_party = self._tax_policy().taxed_party;
+            let sender_tax = match taxed_party {
+                TaxedParty::Sender | TaxedParty::Both => self._calculate_tax(from, amount, tax_e6),
+                TaxedParty::Recipient => 0,
+            };
+            let recipient_tax = match taxed_party {
+                TaxedParty::Recipient | TaxedParty::Both => {
+                    self._calculate_tax(to, amount - sender_tax, tax_e6)
+                }
+                TaxedParty::Sender => 0,
+            };
+            (sender_tax, recipient_tax)
+        }
+
+        // splits tax across destinations by weights, the last destination gets the rounding rest
+        fn _distribute_tax(
+            &mut self,
+            tax: Balance,
+            payer: AccountId,
+            current_denominator_e12: u128,
+        ) {
+            if tax == 0 {
+                return;
+            }
+            let destinations = self._tax_policy().destinations;
+            if destinations.is_empty() {
+                self._add_profit_and_increase_shares_minting_allowance(tax, payer);
+                return;
+            }
+            let total_weight: u128 = destinations.iter().map(|d| d.1 as u128).sum();
+            let mut rest = tax;
+            for (index, (destination, weight)) in destinations.iter().enumerate() {
+                let part = if index == destinations.len() - 1 {
+                    rest
+                } else {
+                    tax * *weight as u128 / total_weight
+                };
+                rest -= part;
+                match destination {
+                    TaxDestination::Profit => {
+                        self._add_profit_and_increase_shares_minting_allowance(part, payer)
+                    }
+                    TaxDestination::Account(account) => {
+                        self._increase_balance(*account, part, current_denominator_e12);
+                        self._emit_transfer_event(Some(payer), Some(*account), part);
+                    }
+                    TaxDestination::Burn => self._emit_transfer_event(Some(payer), None, part),
+                }
+            }
+        }
+
+        // removes scaled_amount from scaled supply and returns by how much rated supply dropped
+        fn _sub_scaled_rated_supply(
+            &mut self,
+            scaled_amount: Balance,
+            current_denominator_e12: u128,
+        ) -> Balance {
+            let rated_supply_before =
+                _rated_amount(self.scaled_rated_supply, current_denominator_e12);
+            self.scaled_rated_supply -= scaled_amount;
+            rated_supply_before - _rated_amount(self.scaled_rated_supply, current_denominator_e12)
+        }
+
+        // default policy until owner sets one
+        fn _tax_policy(&self) -> TaxPolicy {
+            self.tax_policy.get(&()).unwrap_or_default()
+        }
+
+        fn _is_frozen(&self, account: &AccountId) -> bool {
+            self.is_frozen.get(account).unwrap_or(false)
+        }
+
+        // permits signed for other contracts or other chains (the same address can be deployed to many) can't be used here
+        fn _domain_separator(&self) -> [u8; 32] {
+            _blake2x256(
+                &(
+                    PERMIT_DOMAIN,
+                    self.chain_id.get(&()),
+                    self.env().account_id(),
+                )
+                    .encode(),
+            )
+        }
+
+        fn _permit_hash(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            nonce: u64,
+            deadline: Timestamp,
+        ) -> [u8; 32] {
+            _blake2x256(
+                &(
+                    self._domain_separator(),
+                    owner,
+                    spender,
+                    value,
+                    nonce,
+                    deadline,
+                )
+                    .encode(),
+            )
+        }
+
+        // burns amount + fee approved by receiver of flash loan, fee is added to profit
+        fn _settle_flash_loan(
+            &mut self,
+            receiver: AccountId,
+            amount: Balance,
+            fee: Balance,
+        ) -> Result<(), FlashLendingError> {
+            let lender = self.env().account_id();
+            let allowance = self.allowance(receiver, lender);
+            if allowance < amount + fee {
+                return Err(FlashLendingError::PSP22Error(
+                    PSP22Error::InsufficientAllowance,
+                ));
+            }
+            self._approve_from_to(receiver, lender, allowance - amount - fee)?;
+            self._burn_from(receiver, amount + fee)?;
+            self._add_profit_and_increase_shares_minting_allowance(fee, receiver);
+            Ok(())
+        }
+
+        // fee is rounded up
+        fn _flash_fee(&self, amount: Balance) -> Balance {
+            (amount * self.flash_fee_e6 + E6 - 1) / E6
+        }
+    }
 
     impl PSP22Rated for StableCoinContract {
         #[ink(message)]
+        #[modifiers(when_migrated)]
         fn update_current_denominator_e12(&mut self) -> u128 {
             self._update_current_denominator_e12()
         }
 
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_owner)]
         fn set_is_unrated(&mut self, account: AccountId, set_to: bool) -> Result<(), PSP22Error> {
             let is_unrated: bool = self._is_unrated(&account);
@@ -388,6 +1087,7 @@ pub mod stable_coin {
         }
 
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_owner)]
         fn set_is_tax_free(&mut self, account: AccountId, set_to: bool) -> Result<(), PSP22Error> {
             self.is_tax_free.insert(&account, &set_to);
@@ -395,6 +1095,20 @@ pub mod stable_coin {
         }
 
         #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_tax_policy(&mut self, tax_policy: TaxPolicy) -> Result<(), PSP22Error> {
+            if !tax_policy.destinations.is_empty()
+                && tax_policy.destinations.iter().all(|d| d.1 == 0)
+            {
+                return Err(PSP22Error::Custom(String::from("ZeroTaxWeights")));
+            }
+            self.tax_policy.insert(&(), &tax_policy);
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_owner)]
         fn set_stable_controller_address(
             &mut self,
@@ -405,6 +1119,7 @@ pub mod stable_coin {
         }
 
         #[ink(message)]
+        #[modifiers(when_migrated)]
         fn be_controlled(
             &mut self,
             interest_rate_e12: i128,
@@ -420,6 +1135,7 @@ pub mod stable_coin {
         }
 
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_role(VAULT))]
         fn add_account_debt(
             &mut self,
@@ -432,6 +1148,7 @@ pub mod stable_coin {
         }
 
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_role(VAULT))]
         fn sub_account_debt(
             &mut self,
@@ -447,7 +1164,15 @@ pub mod stable_coin {
     impl PSP22RatedView for StableCoinContract {
         #[ink(message)]
         fn rated_supply(&self) -> Balance {
-            self.rated_supply
+            _rated_amount(
+                self.scaled_rated_supply,
+                self._get_current_denominator_e12(),
+            )
+        }
+
+        #[ink(message)]
+        fn scaled_rated_supply(&self) -> Balance {
+            self.scaled_rated_supply
         }
 
         #[ink(message)]
@@ -457,7 +1182,7 @@ pub mod stable_coin {
 
         #[ink(message)]
         fn current_denominator_e12(&self) -> u128 {
-            self.current_denominator_e12
+            self._get_current_denominator_e12()
         }
 
         #[ink(message)]
@@ -466,8 +1191,11 @@ pub mod stable_coin {
         }
 
         #[ink(message)]
-        fn applied_denominator_e12(&self, account: AccountId) -> Balance {
-            self._applied_denominator_e12(&account)
+        fn scaled_balance_of(&self, account: AccountId) -> Balance {
+            if self._is_unrated(&account) {
+                return 0;
+            }
+            self._raw_balance_of(&account)
         }
 
         #[ink(message)]
@@ -495,6 +1223,11 @@ pub mod stable_coin {
             self._is_tax_free(&account)
         }
 
+        #[ink(message)]
+        fn tax_policy(&self) -> TaxPolicy {
+            self._tax_policy()
+        }
+
         #[ink(message)]
         fn account_debt(&self, account: AccountId) -> Balance {
             self._account_debt(&account)
@@ -502,7 +1235,8 @@ pub mod stable_coin {
     }
 
     impl PSP22RatedInternals for StableCoinContract {
-        fn _unupdated_balance_of(&self, account: &AccountId) -> Balance {
+        // scaled balance for rated accounts, nominal balance for unrated accounts
+        fn _raw_balance_of(&self, account: &AccountId) -> Balance {
             self.psp22.balances.get(account).unwrap_or(0)
         }
 
@@ -510,207 +1244,134 @@ pub mod stable_coin {
             self.is_unrated.get(account).unwrap_or(false)
         }
 
-        fn _applied_denominator_e12(&self, account: &AccountId) -> u128 {
-            self.applied_denominator_e12.get(account).unwrap_or(0) //TODO check
-        }
-
+        // set by owner for the account or given by any of exempt roles
         fn _is_tax_free(&self, account: &AccountId) -> bool {
             self.is_tax_free.get(account).unwrap_or(false)
+                || self
+                    ._tax_policy()
+                    .exempt_roles
+                    .iter()
+                    .any(|role| self.has_role(*role, *account))
         }
 
         fn _account_debt(&self, account: &AccountId) -> Balance {
             self.account_debt.get(account).unwrap_or(0)
         }
 
-        fn _update_current_denominator_e12(&mut self) -> u128 {
-            let updated_denominator = self.current_denominator_e12
-                * (((self.env().block_timestamp() - self.last_current_denominator_update_timestamp)
-                    as i128
-                    * self.current_interest_rate_e12) as u128);
+        // calculates denominator at current timestamp without updating storage
+        fn _get_current_denominator_e12(&self) -> u128 {
+            let timestamp = self.env().block_timestamp();
+            let last_timestamp = self.last_current_denominator_update_timestamp;
+            if timestamp <= last_timestamp {
+                return self.current_denominator_e12;
+            }
+            _compound_denominator_e12(
+                self.current_denominator_e12,
+                self.current_interest_rate_e12,
+                timestamp - last_timestamp,
+            )
+        }
 
-            self.current_denominator_e12 = updated_denominator;
-            updated_denominator
+        // change of rated supply caused by the new denominator is profit (positive rate) or loss (negative rate)
+        fn _update_current_denominator_e12(&mut self) -> u128 {
+            let current_denominator_e12 = self._get_current_denominator_e12();
+            let rated_supply_before =
+                _rated_amount(self.scaled_rated_supply, self.current_denominator_e12);
+            let rated_supply_after =
+                _rated_amount(self.scaled_rated_supply, current_denominator_e12);
+            if rated_supply_before > rated_supply_after {
+                self._add_profit(rated_supply_before - rated_supply_after);
+            } else if rated_supply_before < rated_supply_after {
+                self._sub_profit(rated_supply_after - rated_supply_before);
+            }
+            self.current_denominator_e12 = current_denominator_e12;
+            self.last_current_denominator_update_timestamp = self.env().block_timestamp();
+            current_denominator_e12
         }
 
         fn _switch_is_unrated(&mut self, account: AccountId) -> Result<(), PSP22Error> {
+            self._update_account_snapshot(account);
+            self._update_supply_snapshot();
             let current_denominator_e12 = self._update_current_denominator_e12();
+            let raw_balance = self._raw_balance_of(&account);
             if self._is_unrated(&account) {
+                self.unrated_supply -= raw_balance;
+                self.psp22.balances.insert(&account, &0);
                 self.is_unrated.insert(&account, &(false));
+                self._increase_balance(account, raw_balance, current_denominator_e12);
             } else {
-                let unupdated_balance = self._unupdated_balance_of(&account);
-                let applied_denominator_e12 = self._applied_denominator_e12(&account);
-                // negative interest rates
-                if current_denominator_e12 > applied_denominator_e12 {
-                    let denominator_difference_e12 =
-                        current_denominator_e12 - applied_denominator_e12;
-                    let to_substract = unupdated_balance * denominator_difference_e12
-                        / current_denominator_e12
-                        + 1; //round up
-                    self.psp22
-                        .balances
-                        .insert(&account, &(unupdated_balance - to_substract));
-                    self._add_profit_and_increase_shares_minting_allowance(to_substract, account);
-                    self.rated_supply -= to_substract;
-                    // positive interest rates
-                } else if current_denominator_e12 < applied_denominator_e12 {
-                    let denominator_difference_e12 =
-                        applied_denominator_e12 - current_denominator_e12;
-                    let to_add = unupdated_balance * denominator_difference_e12
-                        / current_denominator_e12
-                        - 1; //round down
-                    self.psp22
-                        .balances
-                        .insert(&account, &(unupdated_balance + to_add));
-                    self._sub_profit(to_add);
-                    self.rated_supply += to_add;
-                }
+                let balance = _rated_amount(raw_balance, current_denominator_e12);
+                let debited = self._sub_scaled_rated_supply(raw_balance, current_denominator_e12);
+                self._add_profit(debited - balance);
+                self.psp22.balances.insert(&account, &balance);
+                self.unrated_supply += balance;
                 self.is_unrated.insert(&account, &(true));
             }
             Ok(())
         }
 
-        // fn _update_balance_of(&mut self, account: AccountId) {
-        //     let unupdated_balance: Balance = self._unupdated_balance_of(&account);
-
-        //     if !self._is_unrated(&account) {
-        //         let applied_denominator_e12 = self._applied_denominator_e12(&account);
-        //         // negative interest rates
-        //         if current_denominator_e12 > applied_denominator_e12 {
-        //             let denominator_difference_e12 =
-        //                 current_denominator_e12 - applied_denominator_e12;
-        //             let to_substract = unupdated_balance * denominator_difference_e12
-        //                 / current_denominator_e12
-        //                 + 1; //round up
-        //             self._add_profit_and_increase_shares_minting_allowance(to_substract, account);
-        //             self.rated_supply = self.rated_supply - to_substract;
-        //             // positive interest rates
-        //         } else if current_denominator_e12 < applied_denominator_e12 {
-        //             let denominator_difference_e12 =
-        //                 applied_denominator_e12 - current_denominator_e12;
-        //             let to_add = unupdated_balance * denominator_difference_e12
-        //                 / current_denominator_e12
-        //                 - 1; //round down
-        //             self._sub_profit(to_add);
-        //             self.rated_supply = self.rated_supply + to_add;
-        //         }
-        //     }
-        //     self.applied_denominator_e12
-        //         .insert(&account, &current_denominator_e12);
-        // }
-
+        // rated account gets scaled amount rounded down, so the balance grows by at most amount
         fn _increase_balance(
             &mut self,
             account: AccountId,
             amount: Balance,
             current_denominator_e12: u128,
         ) {
-            let unupdated_balance: Balance = self._unupdated_balance_of(&account);
-
-            if !self._is_unrated(&account) {
-                let applied_denominator_e12 = self._applied_denominator_e12(&account);
-                // negative interest rates
-                if current_denominator_e12 > applied_denominator_e12 {
-                    let denominator_difference_e12 =
-                        current_denominator_e12 - applied_denominator_e12;
-                    let to_substract = unupdated_balance * denominator_difference_e12
-                        / current_denominator_e12
-                        + 1; //round up
-                    self.psp22
-                        .balances
-                        .insert(&account, &(unupdated_balance - to_substract + amount));
-                    self._add_profit_and_increase_shares_minting_allowance(to_substract, account);
-                    self.rated_supply = self.rated_supply - to_substract + amount;
-                    // positive interest rates
-                } else if current_denominator_e12 < applied_denominator_e12 {
-                    let denominator_difference_e12 =
-                        applied_denominator_e12 - current_denominator_e12;
-                    let to_add = unupdated_balance * denominator_difference_e12
-                        / current_denominator_e12
-                        - 1; //round down
-                    self.psp22
-                        .balances
-                        .insert(&account, &(unupdated_balance + to_add + amount));
-                    self._sub_profit(to_add);
-                    self.rated_supply = self.rated_supply + to_add + amount;
-                } else {
-                    self.psp22
-                        .balances
-                        .insert(&account, &(unupdated_balance + amount));
-                    self.rated_supply += amount;
-                }
-            } else {
+            self._update_account_snapshot(account);
+            self._update_supply_snapshot();
+            let raw_balance: Balance = self._raw_balance_of(&account);
+
+            if self._is_unrated(&account) {
                 self.psp22
                     .balances
-                    .insert(&account, &(unupdated_balance + amount));
+                    .insert(&account, &(raw_balance + amount));
                 self.unrated_supply += amount;
+            } else {
+                let scaled_amount = amount * current_denominator_e12 / E12;
+                let rated_supply_before =
+                    _rated_amount(self.scaled_rated_supply, current_denominator_e12);
+                self.scaled_rated_supply += scaled_amount;
+                let credited = _rated_amount(self.scaled_rated_supply, current_denominator_e12)
+                    - rated_supply_before;
+                self.psp22
+                    .balances
+                    .insert(&account, &(raw_balance + scaled_amount));
+                // rounding dust that did not get to rated supply
+                self._add_profit(amount - credited);
             }
-            self.applied_denominator_e12
-                .insert(&account, &current_denominator_e12);
         }
 
+        // rated account loses scaled amount rounded up, so the balance drops by at least amount
         fn _decrease_balance(
             &mut self,
             account: AccountId,
             amount: Balance,
             current_denominator_e12: u128,
         ) -> Result<(), PSP22Error> {
-            let unupdated_balance: Balance = self._unupdated_balance_of(&account);
-
-            if !self._is_unrated(&account) {
-                let applied_denominator_e12 = self._applied_denominator_e12(&account);
-                // negative interest rates
-                if current_denominator_e12 > applied_denominator_e12 {
-                    let denominator_difference_e12 =
-                        current_denominator_e12 - applied_denominator_e12;
-                    let to_substract = unupdated_balance * denominator_difference_e12
-                        / current_denominator_e12
-                        + 1; //round up
-                    let updated_balance = unupdated_balance - to_substract;
-                    if amount > updated_balance {
-                        return Err(PSP22Error::InsufficientBalance);
-                    }
-                    self.psp22
-                        .balances
-                        .insert(&account, &(updated_balance - amount));
-                    self._add_profit_and_increase_shares_minting_allowance(to_substract, account); // OK, this comes from tax not rates
-                    self.rated_supply = self.rated_supply - to_substract - amount;
-                    // positive interest rates
-                } else if current_denominator_e12 < applied_denominator_e12 {
-                    let denominator_difference_e12 =
-                        applied_denominator_e12 - current_denominator_e12;
-                    let to_add = unupdated_balance * denominator_difference_e12
-                        / current_denominator_e12
-                        - 1; //round down
-                    let updated_balance = unupdated_balance + to_add;
-                    if amount > updated_balance {
-                        return Err(PSP22Error::InsufficientBalance);
-                    }
-                    self.psp22
-                        .balances
-                        .insert(&account, &(updated_balance + amount));
-                    self._sub_profit(to_add);
-                    self.rated_supply = self.rated_supply + to_add - amount;
-                } else {
-                    if amount > unupdated_balance {
-                        return Err(PSP22Error::InsufficientBalance);
-                    }
-                    self.psp22
-                        .balances
-                        .insert(&account, &(unupdated_balance - amount));
-                    self.rated_supply -= amount;
+            self._update_account_snapshot(account);
+            self._update_supply_snapshot();
+            let raw_balance: Balance = self._raw_balance_of(&account);
+
+            if self._is_unrated(&account) {
+                if amount > raw_balance {
+                    return Err(PSP22Error::InsufficientBalance);
                 }
+                self.psp22
+                    .balances
+                    .insert(&account, &(raw_balance - amount));
+                self.unrated_supply -= amount;
             } else {
-                if amount > unupdated_balance {
+                let scaled_amount = (amount * current_denominator_e12 + E12 - 1) / E12;
+                if scaled_amount > raw_balance {
                     return Err(PSP22Error::InsufficientBalance);
                 }
+                let debited = self._sub_scaled_rated_supply(scaled_amount, current_denominator_e12);
                 self.psp22
                     .balances
-                    .insert(&account, &(unupdated_balance - amount));
-                self.unrated_supply -= amount;
+                    .insert(&account, &(raw_balance - scaled_amount));
+                // rounding dust that left rated supply above amount
+                self._add_profit(debited - amount);
             }
-            self.applied_denominator_e12
-                .insert(&account, &current_denominator_e12);
-
             Ok(())
         }
 
@@ -727,7 +1388,7 @@ pub mod stable_coin {
                 if account_debt >= account_balance {
                     return 0;
                 } else {
-                    let taxed_amount = account_balance - account_debt;
+                    let taxed_amount = (account_balance - account_debt).min(amount);
                     return taxed_amount * tax_e6 / E6;
                 }
             }
@@ -749,6 +1410,45 @@ pub mod stable_coin {
         by: Option<AccountId>,
     }
 
+    #[ink(event)]
+    pub struct Snapshot {
+        id: u32,
+    }
+
+    #[ink(event)]
+    pub struct Frozen {
+        #[ink(topic)]
+        account: AccountId,
+        by: AccountId,
+    }
+    #[ink(event)]
+    pub struct Unfrozen {
+        #[ink(topic)]
+        account: AccountId,
+        by: AccountId,
+    }
+    #[ink(event)]
+    pub struct FrozenWiped {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+        by: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct MinterQuotaSet {
+        #[ink(topic)]
+        minter: AccountId,
+        quota: Option<MinterQuota>,
+    }
+    // outstanding or window capacity of minter is used up, next mint fails until burn or window release
+    #[ink(event)]
+    pub struct MintCapacityExhausted {
+        #[ink(topic)]
+        minter: AccountId,
+        outstanding: Balance,
+    }
+
     #[ink(event)]
     pub struct Transfer {
         #[ink(topic)]
@@ -767,6 +1467,16 @@ pub mod stable_coin {
         value: Balance,
     }
 
+    #[ink(event)]
+    pub struct FlashLoan {
+        #[ink(topic)]
+        initiator: AccountId,
+        #[ink(topic)]
+        receiver: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
+
     #[ink(event)]
     pub struct OwnershipTransferred {
         #[ink(topic)]
@@ -809,6 +1519,68 @@ pub mod stable_coin {
     // tests
     //
 
+    // rated balance of scaled_amount, rounded down
+    fn _rated_amount(scaled_amount: Balance, denominator_e12: u128) -> Balance {
+        scaled_amount * E12 / denominator_e12
+    }
+
+    // denominator * (1 + rate)^dt, positive rate makes rated balances shrink and negative grow
+    fn _compound_denominator_e12(
+        denominator_e12: u128,
+        interest_rate_e12: i128,
+        time_passed: Timestamp,
+    ) -> u128 {
+        let base_e12: u128 = (E12 as i128 + interest_rate_e12).max(0) as u128;
+        // denominator can't reach 0, balances would be infinite
+        (denominator_e12.saturating_mul(rpow_e12(base_e12, time_passed)) / E12).max(1)
+    }
+
+    fn _blake2x256(input: &[u8]) -> [u8; 32] {
+        let mut output = <Blake2x256 as HashOutput>::Type::default();
+        ink_env::hash_bytes::<Blake2x256>(input, &mut output);
+        output
+    }
+
+    #[ink(event)]
+    pub struct Upgraded {
+        old_code_hash: Option<[u8; 32]>,
+        new_code_hash: [u8; 32],
+    }
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    impl UpgradingInternal for StableCoinContract {
+        fn _code_storage_version(&self) -> u32 {
+            STORAGE_VERSION
+        }
+
+        fn _migrate(&mut self, from_version: u32) -> Result<(), UpgradingError> {
+            // balances of version 1 can't be converted to scaled balances without enumerating accounts,
+            // so contracts with version 1 storage can't be upgraded in place and have to be redeployed
+            if from_version < 2 {
+                return Err(UpgradingError::MigrationFailed);
+            }
+            Ok(())
+        }
+
+        fn _emit_upgraded_event(&self, _old_code_hash: Option<[u8; 32]>, _new_code_hash: [u8; 32]) {
+            self.env().emit_event(Upgraded {
+                old_code_hash: _old_code_hash,
+                new_code_hash: _new_code_hash,
+            });
+        }
+
+        fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32) {
+            self.env().emit_event(Migrated {
+                from_version: _from_version,
+                to_version: _to_version,
+            });
+        }
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
@@ -847,5 +1619,578 @@ pub mod stable_coin {
             let emitted_events = ink_env::test::recorded_events().collect::<Vec<_>>();
             assert_eq!(2, emitted_events.len());
         }
+
+        /// RATED ACCOUNTING TESTS
+
+        // xorshift, so property tests are reproducible without extra dependencies
+        fn next_random(seed: &mut u64) -> u64 {
+            *seed ^= *seed << 13;
+            *seed ^= *seed >> 7;
+            *seed ^= *seed << 17;
+            *seed
+        }
+
+        fn setup() -> StableCoinContract {
+            let accounts = accounts();
+            change_caller(accounts.alice);
+            StableCoinContract::new(None, None, DECIMALS, accounts.bob, accounts.alice)
+        }
+
+        // supply is derived from the same scaled state as balances, rounding can only leave
+        // less than 1 unit per rated holder in rated supply
+        fn assert_supply_consistent(instance: &StableCoinContract, holders: &[AccountId]) {
+            let total_supply = instance.total_supply();
+            assert_eq!(
+                total_supply,
+                instance.rated_supply() + instance.unrated_supply()
+            );
+            let balances_sum: Balance = holders.iter().map(|h| instance.balance_of(*h)).sum();
+            let rated_holders =
+                holders.iter().filter(|h| !instance.is_unrated(**h)).count() as u128;
+            assert!(balances_sum <= total_supply);
+            assert!(total_supply - balances_sum <= rated_holders);
+        }
+
+        #[ink::test]
+        fn positive_rate_compounds_denominator() {
+            let accounts = accounts();
+            let mut instance = setup();
+            assert!(instance._mint(accounts.charlie, 1_000_000 * E6).is_ok());
+            instance._update_current_denominator_e12();
+            instance.current_interest_rate_e12 = 1_000_000; // 10^-6 per timestamp unit
+            for _ in 0..10 {
+                ink_env::test::advance_block::<DefaultEnvironment>();
+            }
+            let time_passed = ink_env::block_timestamp::<DefaultEnvironment>()
+                - instance.last_current_denominator_update_timestamp;
+            let expected_denominator_e12 = rpow_e12(E12 + 1_000_000, time_passed);
+            assert_eq!(instance.current_denominator_e12(), expected_denominator_e12);
+            assert!(expected_denominator_e12 > E12);
+            assert_eq!(
+                instance.balance_of(accounts.charlie),
+                1_000_000 * E6 * E12 / expected_denominator_e12
+            );
+            // balance is lowered without any write, profit is booked on update
+            let paid = 1_000_000 * E6 - instance.balance_of(accounts.charlie);
+            instance.update_current_denominator_e12();
+            assert_eq!(instance.get_generated_profit(), paid as i128);
+            assert_eq!(
+                instance.last_current_denominator_update_timestamp,
+                ink_env::block_timestamp::<DefaultEnvironment>()
+            );
+        }
+
+        #[ink::test]
+        fn decrease_balance_subtracts_under_negative_rate() {
+            let accounts = accounts();
+            let mut instance = setup();
+            assert!(instance._mint(accounts.charlie, 1_000 * E6).is_ok());
+            instance._update_current_denominator_e12();
+            instance.current_interest_rate_e12 = -1_000_000;
+            ink_env::test::advance_block::<DefaultEnvironment>();
+            let balance = instance.balance_of(accounts.charlie);
+            assert!(balance > 1_000 * E6);
+            assert!(instance
+                ._transfer_balance(accounts.charlie, accounts.django, 100 * E6)
+                .is_ok());
+            assert!(instance.balance_of(accounts.charlie) <= balance - 100 * E6);
+            assert!(instance.balance_of(accounts.charlie) + 2 >= balance - 100 * E6);
+            assert!(instance.balance_of(accounts.django) <= 100 * E6);
+            assert!(instance.balance_of(accounts.django) + 2 >= 100 * E6);
+            assert_eq!(
+                instance._transfer_balance(accounts.django, accounts.charlie, 101 * E6),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn version_1_storage_is_not_migrated() {
+            let mut instance = setup();
+            instance.upgrading.storage_version = 1;
+            assert_eq!(instance.migrate(), Err(UpgradingError::MigrationFailed));
+            assert_eq!(instance.get_storage_version(), 1);
+        }
+
+        #[ink::test]
+        fn missing_tax_policy_and_chain_id_read_as_defaults() {
+            let instance = setup();
+            assert_eq!(instance.tax_policy.get(&()), None);
+            assert_eq!(instance.tax_policy(), TaxPolicy::default());
+            assert_eq!(instance.chain_id(), None);
+        }
+
+        #[ink::test]
+        fn tax_policy_exempts_roles_and_splits_tax() {
+            let accounts = accounts();
+            let mut instance = setup();
+            assert!(instance._mint(accounts.charlie, 2_000 * E6).is_ok());
+            assert!(instance.grant_role(VAULT, accounts.frank).is_ok());
+            instance.tax_e6 = E6 / 100;
+            assert!(instance
+                .set_tax_policy(TaxPolicy {
+                    taxed_party: TaxedParty::Both,
+                    exempt_roles: vec![VAULT],
+                    destinations: vec![
+                        (TaxDestination::Account(accounts.eve), 1),
+                        (TaxDestination::Burn, 1),
+                        (TaxDestination::Profit, 2),
+                    ],
+                })
+                .is_ok());
+
+            // sender is taxed, recipient is exempt by role
+            assert!(instance
+                ._transfer_balance(accounts.charlie, accounts.frank, 1_000 * E6)
+                .is_ok());
+            assert_eq!(instance.balance_of(accounts.frank), 990 * E6);
+            assert_eq!(instance.balance_of(accounts.eve), 10 * E6 / 4);
+            assert_eq!(instance.get_generated_profit(), (10 * E6 / 2) as i128);
+            // profit and burn parts leave supply, account part stays
+            assert_eq!(instance.total_supply(), 2_000 * E6 - 10 * E6 + 10 * E6 / 4);
+
+            // both parties are taxed
+            assert!(instance
+                ._transfer_balance(accounts.charlie, accounts.django, 1_000 * E6)
+                .is_ok());
+            assert_eq!(instance.balance_of(accounts.django), 980 * E6 + 100_000);
+
+            assert_eq!(
+                instance.set_tax_policy(TaxPolicy {
+                    taxed_party: TaxedParty::Recipient,
+                    exempt_roles: vec![],
+                    destinations: vec![(TaxDestination::Burn, 0)],
+                }),
+                Err(PSP22Error::Custom(String::from("ZeroTaxWeights")))
+            );
+        }
+
+        #[ink::test]
+        fn mint_respects_minter_quota() {
+            let accounts = accounts();
+            let mut instance = setup();
+            assert!(instance.grant_role(MINTER, accounts.alice).is_ok());
+            assert!(instance.grant_role(BURNER, accounts.alice).is_ok());
+            assert_eq!(
+                instance.remaining_mint_capacity(accounts.alice),
+                Balance::MAX
+            );
+            assert!(instance
+                .set_minter_quota(
+                    accounts.alice,
+                    Some(MinterQuota {
+                        max_outstanding: 1_000,
+                        window_limit: 600,
+                        window: 1_000_000,
+                    }),
+                )
+                .is_ok());
+
+            assert!(instance.mint(accounts.charlie, 500).is_ok());
+            assert_eq!(instance.remaining_mint_capacity(accounts.alice), 100);
+            assert_eq!(
+                instance.mint(accounts.charlie, 200),
+                Err(MintLimitError::QuotaExceeded.into())
+            );
+            assert_eq!(instance.balance_of(accounts.charlie), 500);
+
+            // without window limit only outstanding amount is capped, burning frees it
+            assert!(instance
+                .set_minter_quota(
+                    accounts.alice,
+                    Some(MinterQuota {
+                        max_outstanding: 1_000,
+                        window_limit: 0,
+                        window: 0,
+                    }),
+                )
+                .is_ok());
+            assert!(instance.mint(accounts.charlie, 500).is_ok());
+            assert!(instance.mint(accounts.charlie, 1).is_err());
+            assert!(instance.burn(accounts.charlie, 300).is_ok());
+            assert_eq!(instance.minter_outstanding(accounts.alice), 700);
+            assert_eq!(instance.remaining_mint_capacity(accounts.alice), 300);
+        }
+
+        fn decoded_events() -> Vec<Event> {
+            ink_env::test::recorded_events()
+                .map(|event| <Event as scale::Decode>::decode(&mut &event.data[..]).unwrap())
+                .collect()
+        }
+
+        #[ink::test]
+        fn minter_quota_events_are_emitted() {
+            let accounts = accounts();
+            let mut instance = setup();
+            assert!(instance.grant_role(MINTER, accounts.alice).is_ok());
+            let quota = MinterQuota {
+                max_outstanding: 1_000,
+                window_limit: 600,
+                window: 1_000_000,
+            };
+            assert!(instance
+                .set_minter_quota(accounts.alice, Some(quota))
+                .is_ok());
+            match decoded_events().last() {
+                Some(Event::MinterQuotaSet(event)) => {
+                    assert_eq!(event.minter, accounts.alice);
+                    assert_eq!(event.quota, Some(quota));
+                }
+                _ => panic!("MinterQuotaSet not emitted"),
+            }
+
+            // mint below capacity emits no exhaustion
+            assert!(instance.mint(accounts.charlie, 500).is_ok());
+            assert!(!decoded_events()
+                .iter()
+                .any(|event| matches!(event, Event::MintCapacityExhausted(_))));
+
+            // window capacity used up
+            assert!(instance.mint(accounts.charlie, 100).is_ok());
+            match decoded_events().last() {
+                Some(Event::MintCapacityExhausted(event)) => {
+                    assert_eq!(event.minter, accounts.alice);
+                    assert_eq!(event.outstanding, 600);
+                }
+                _ => panic!("MintCapacityExhausted not emitted"),
+            }
+
+            // outstanding capacity used up
+            assert!(instance
+                .set_minter_quota(
+                    accounts.alice,
+                    Some(MinterQuota {
+                        max_outstanding: 1_000,
+                        window_limit: 0,
+                        window: 0,
+                    }),
+                )
+                .is_ok());
+            assert!(instance.mint(accounts.charlie, 400).is_ok());
+            match decoded_events().last() {
+                Some(Event::MintCapacityExhausted(event)) => {
+                    assert_eq!(event.minter, accounts.alice);
+                    assert_eq!(event.outstanding, 1_000);
+                }
+                _ => panic!("MintCapacityExhausted not emitted"),
+            }
+
+            // removed quota is reported as None
+            assert!(instance.set_minter_quota(accounts.alice, None).is_ok());
+            match decoded_events().last() {
+                Some(Event::MinterQuotaSet(event)) => assert_eq!(event.quota, None),
+                _ => panic!("MinterQuotaSet not emitted"),
+            }
+        }
+
+        #[ink::test]
+        fn transfer_batch_is_atomic() {
+            let accounts = accounts();
+            let mut instance = setup();
+            assert!(instance._mint(accounts.charlie, 1_000).is_ok());
+
+            assert_eq!(
+                instance._transfer_batch_balance(
+                    accounts.charlie,
+                    &[(accounts.django, 600), (accounts.eve, 401)],
+                ),
+                Err(PSP22Error::InsufficientBalance)
+            );
+            assert_eq!(instance.balance_of(accounts.charlie), 1_000);
+            assert_eq!(instance.balance_of(accounts.django), 0);
+
+            assert!(instance
+                ._transfer_batch_balance(
+                    accounts.charlie,
+                    &[(accounts.django, 600), (accounts.eve, 400)],
+                )
+                .is_ok());
+            assert_eq!(instance.balance_of(accounts.charlie), 0);
+            assert_eq!(instance.balance_of(accounts.django), 600);
+            assert_eq!(instance.balance_of(accounts.eve), 400);
+        }
+
+        #[ink::test]
+        fn supply_and_profit_stay_consistent() {
+            let accounts = accounts();
+            let holders = [
+                accounts.charlie,
+                accounts.django,
+                accounts.eve,
+                accounts.frank,
+            ];
+            for initial_seed in 1..=8_u64 {
+                let mut seed = initial_seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                let mut instance = setup();
+                let mut minted: Balance = 0;
+                for step in 0..60 {
+                    let holder = holders[(next_random(&mut seed) % 4) as usize];
+                    let other = holders[(next_random(&mut seed) % 4) as usize];
+                    match next_random(&mut seed) % 6 {
+                        0 => {
+                            let amount = (next_random(&mut seed) % (1_000 * E6 as u64)) as u128;
+                            assert!(instance._mint(holder, amount).is_ok());
+                            minted += amount;
+                        }
+                        1 => {
+                            let balance = instance.balance_of(holder);
+                            let amount = next_random(&mut seed) as u128 % (balance + 1);
+                            assert!(instance._transfer_balance(holder, other, amount).is_ok());
+                        }
+                        2 => {
+                            instance._update_current_denominator_e12();
+                            instance.current_interest_rate_e12 =
+                                (next_random(&mut seed) % 2_000_001) as i128 - 1_000_000;
+                        }
+                        3 => ink_env::test::advance_block::<DefaultEnvironment>(),
+                        4 => assert!(instance._switch_is_unrated(holder).is_ok()),
+                        _ => {
+                            instance.tax_e6 = (next_random(&mut seed) % 3) as u128 * E6 / 100;
+                            let balance = instance.balance_of(holder);
+                            assert!(instance._transfer_balance(holder, other, balance).is_ok());
+                        }
+                    }
+                    assert_supply_consistent(&instance, &holders);
+                    // every unit that is not in supply was booked as profit or loss
+                    instance.update_current_denominator_e12();
+                    assert_eq!(
+                        instance.total_supply() as i128 + instance.get_generated_profit(),
+                        minted as i128,
+                        "seed {} step {}",
+                        initial_seed,
+                        step
+                    );
+                }
+            }
+        }
+
+        #[ink::test]
+        fn flash_loan_settlement_books_fee() {
+            let accounts = accounts();
+            let mut instance = setup();
+            let lender = instance.env().account_id();
+            assert!(instance.set_flash_fee_e6(E6 / 100).is_ok());
+            let amount = 1_000 * E6;
+            let fee = instance.flash_fee(amount);
+            assert_eq!(fee, 10 * E6);
+
+            // loan minted to receiver, which earned the fee elsewhere and approved repayment
+            assert!(instance._mint(accounts.charlie, amount + fee).is_ok());
+            assert!(instance
+                ._approve_from_to(accounts.charlie, lender, amount + fee + 1)
+                .is_ok());
+            assert!(instance
+                ._settle_flash_loan(accounts.charlie, amount, fee)
+                .is_ok());
+            assert_eq!(instance.balance_of(accounts.charlie), 0);
+            assert_eq!(instance.allowance(accounts.charlie, lender), 1);
+            assert_eq!(instance.total_supply(), 0);
+            assert_eq!(instance.get_generated_profit(), fee as i128);
+            assert_eq!(instance.get_shares_minting_allowance(accounts.charlie), fee);
+        }
+
+        #[ink::test]
+        fn flash_loan_fails_when_not_repaid() {
+            let accounts = accounts();
+            let mut instance = setup();
+            let lender = instance.env().account_id();
+            assert!(instance.set_flash_fee_e6(E6 / 100).is_ok());
+            let amount = 1_000 * E6;
+            let fee = instance.flash_fee(amount);
+            assert!(instance._mint(accounts.charlie, amount).is_ok());
+
+            // fee not approved
+            assert!(instance
+                ._approve_from_to(accounts.charlie, lender, amount)
+                .is_ok());
+            assert_eq!(
+                instance._settle_flash_loan(accounts.charlie, amount, fee),
+                Err(FlashLendingError::PSP22Error(
+                    PSP22Error::InsufficientAllowance
+                ))
+            );
+            // approved, but fee was not earned
+            assert!(instance
+                ._approve_from_to(accounts.charlie, lender, amount + fee)
+                .is_ok());
+            assert_eq!(
+                instance._settle_flash_loan(accounts.charlie, amount, fee),
+                Err(FlashLendingError::PSP22Error(
+                    PSP22Error::InsufficientBalance
+                ))
+            );
+            assert_eq!(instance.get_generated_profit(), 0);
+
+            assert!(instance.set_max_flash_loan(amount - 1).is_ok());
+            assert_eq!(
+                instance.flash_loan(accounts.charlie, amount, Vec::new()),
+                Err(FlashLendingError::AmountAboveMaximum)
+            );
+        }
+
+        #[ink::test]
+        fn permit_checks_deadline_chain_id_and_signature() {
+            let accounts = accounts();
+            let mut instance = setup();
+            let deadline = ink_env::block_timestamp::<DefaultEnvironment>();
+            ink_env::test::advance_block::<DefaultEnvironment>();
+            assert_eq!(
+                instance.permit(accounts.charlie, accounts.django, 100, deadline, [0; 65]),
+                Err(PermitError::Expired)
+            );
+            assert_eq!(
+                instance.permit(
+                    accounts.charlie,
+                    accounts.django,
+                    100,
+                    Timestamp::MAX,
+                    [0; 65]
+                ),
+                Err(PermitError::ChainIdNotSet)
+            );
+
+            let domain_separator = instance.domain_separator();
+            change_caller(accounts.eve);
+            assert!(instance.set_chain_id([7; 32]).is_err());
+            change_caller(accounts.alice);
+            assert!(instance.set_chain_id([7; 32]).is_ok());
+            assert_eq!(instance.chain_id(), Some([7; 32]));
+            // permits signed for another chain don't match
+            assert_ne!(instance.domain_separator(), domain_separator);
+
+            assert_eq!(
+                instance.permit(
+                    accounts.charlie,
+                    accounts.django,
+                    100,
+                    Timestamp::MAX,
+                    [0; 65]
+                ),
+                Err(PermitError::InvalidSignature)
+            );
+            assert_eq!(instance.nonces(accounts.charlie), 0);
+            assert_eq!(instance.allowance(accounts.charlie, accounts.django), 0);
+        }
+
+        #[ink::test]
+        fn frozen_account_can_not_send_receive_or_be_minted_to() {
+            let accounts = accounts();
+            let mut instance = setup();
+            let frozen = Err(PSP22Error::Custom(String::from("AccountFrozen")));
+            assert!(instance.grant_role(FREEZER, accounts.alice).is_ok());
+            assert!(instance.grant_role(MINTER, accounts.alice).is_ok());
+            assert!(instance._mint(accounts.charlie, 1_000).is_ok());
+            assert!(instance.freeze(accounts.charlie).is_ok());
+            assert!(instance.is_frozen(accounts.charlie));
+
+            assert_eq!(instance.mint(accounts.charlie, 100), frozen);
+            change_caller(accounts.charlie);
+            assert_eq!(instance.transfer(accounts.django, 100, Vec::new()), frozen);
+            assert_eq!(
+                instance.transfer_batch(vec![(accounts.django, 100)], Vec::new()),
+                frozen
+            );
+            change_caller(accounts.alice);
+            assert!(instance._mint(accounts.django, 1_000).is_ok());
+            change_caller(accounts.django);
+            assert_eq!(instance.transfer(accounts.charlie, 100, Vec::new()), frozen);
+            // frozen recipient fails the whole batch, it is first as receiver checks of others are calls
+            assert_eq!(
+                instance.transfer_batch(
+                    vec![(accounts.charlie, 100), (accounts.eve, 100)],
+                    Vec::new()
+                ),
+                frozen
+            );
+            assert_eq!(instance.balance_of(accounts.eve), 0);
+            assert_eq!(instance.balance_of(accounts.charlie), 1_000);
+
+            // only freezer freezes
+            assert!(instance.freeze(accounts.eve).is_err());
+            change_caller(accounts.alice);
+            assert!(instance.unfreeze(accounts.charlie).is_ok());
+            assert!(instance.mint(accounts.charlie, 100).is_ok());
+        }
+
+        #[ink::test]
+        fn only_owner_wipes_frozen_balance() {
+            let accounts = accounts();
+            let mut instance = setup();
+            assert!(instance.grant_role(FREEZER, accounts.bob).is_ok());
+            assert!(instance._mint(accounts.charlie, 1_000).is_ok());
+            assert_eq!(
+                instance.wipe_frozen(accounts.charlie),
+                Err(FreezingError::NotFrozen)
+            );
+
+            change_caller(accounts.bob);
+            assert!(instance.freeze(accounts.charlie).is_ok());
+            // freezer role is not enough to wipe
+            assert_eq!(
+                instance.wipe_frozen(accounts.charlie),
+                Err(FreezingError::OwnableError(OwnableError::CallerIsNotOwner))
+            );
+
+            change_caller(accounts.alice);
+            assert_eq!(instance.wipe_frozen(accounts.charlie), Ok(1_000));
+            assert_eq!(instance.balance_of(accounts.charlie), 0);
+            assert_eq!(instance.total_supply(), 0);
+        }
+
+        #[ink::test]
+        fn snapshot_keeps_balances_of_rated_and_unrated_accounts() {
+            let accounts = accounts();
+            let mut instance = setup();
+            assert!(instance._mint(accounts.charlie, 1_000 * E6).is_ok());
+            assert!(instance._mint(accounts.django, 500 * E6).is_ok());
+            assert!(instance._switch_is_unrated(accounts.django).is_ok());
+            assert_eq!(
+                instance.balance_of_at(accounts.charlie, 1),
+                Err(SnapshottingError::NonexistentSnapshot)
+            );
+            change_caller(accounts.eve);
+            assert!(instance.snapshot().is_err());
+            change_caller(accounts.alice);
+
+            instance._update_current_denominator_e12();
+            instance.current_interest_rate_e12 = 1_000_000;
+            ink_env::test::advance_block::<DefaultEnvironment>();
+            assert_eq!(instance.snapshot(), Ok(1));
+            let charlie_at_1 = instance.balance_of(accounts.charlie);
+            let django_at_1 = instance.balance_of(accounts.django);
+            let supply_at_1 = instance.total_supply();
+            assert!(charlie_at_1 < 1_000 * E6);
+            assert_eq!(django_at_1, 500 * E6);
+
+            // interest, transfers and mints after the snapshot don't change it
+            for _ in 0..5 {
+                ink_env::test::advance_block::<DefaultEnvironment>();
+            }
+            assert!(instance
+                ._transfer_balance(accounts.charlie, accounts.django, 100 * E6)
+                .is_ok());
+            assert!(instance._mint(accounts.eve, 300 * E6).is_ok());
+            assert!(instance._switch_is_unrated(accounts.django).is_ok());
+            assert!(instance.balance_of(accounts.charlie) < charlie_at_1 - 100 * E6);
+
+            assert_eq!(
+                instance.balance_of_at(accounts.charlie, 1),
+                Ok(charlie_at_1)
+            );
+            assert_eq!(instance.balance_of_at(accounts.django, 1), Ok(django_at_1));
+            assert_eq!(instance.balance_of_at(accounts.eve, 1), Ok(0));
+            assert_eq!(instance.total_supply_at(1), Ok(supply_at_1));
+
+            // account untouched since the snapshot reads its current state
+            assert_eq!(instance.snapshot(), Ok(2));
+            assert_eq!(
+                instance.balance_of_at(accounts.eve, 2),
+                Ok(instance.balance_of(accounts.eve))
+            );
+            assert_eq!(instance.total_supply_at(2), Ok(instance.total_supply()));
+            assert_eq!(
+                instance.total_supply_at(3),
+                Err(SnapshottingError::NonexistentSnapshot)
+            );
+        }
     }
 }
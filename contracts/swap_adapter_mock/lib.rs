@@ -0,0 +1,159 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// Constant product pool of two tokens used as swap adapter in tests.
+/// Liquidity is added by transfering tokens to the contract and calling sync.
+#[brush::contract]
+pub mod swap_adapter_mock {
+    use brush::contracts::psp22::*;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::SpreadAllocate;
+    use stable_coin_project::traits::swap_adapter::*;
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate)]
+    pub struct SwapAdapterMockContract {
+        pub token_a: AccountId,
+        pub token_b: AccountId,
+        pub reserve_a: Balance,
+        pub reserve_b: Balance,
+    }
+
+    impl SwapAdapterMockContract {
+        #[ink(constructor)]
+        pub fn new(token_a: AccountId, token_b: AccountId) -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut SwapAdapterMockContract| {
+                instance.token_a = token_a;
+                instance.token_b = token_b;
+            })
+        }
+
+        // sets reserves to current balances of the pool
+        #[ink(message)]
+        pub fn sync(&mut self) {
+            let pool = self.env().account_id();
+            self.reserve_a = PSP22Ref::balance_of(&self.token_a, pool);
+            self.reserve_b = PSP22Ref::balance_of(&self.token_b, pool);
+        }
+
+        #[ink(message)]
+        pub fn get_reserves(&self) -> (Balance, Balance) {
+            (self.reserve_a, self.reserve_b)
+        }
+
+        fn _get_reserves(
+            &self,
+            token_in: AccountId,
+            token_out: AccountId,
+        ) -> Result<(Balance, Balance), SwapAdapterError> {
+            if token_in == self.token_a && token_out == self.token_b {
+                Ok((self.reserve_a, self.reserve_b))
+            } else if token_in == self.token_b && token_out == self.token_a {
+                Ok((self.reserve_b, self.reserve_a))
+            } else {
+                Err(SwapAdapterError::UnsupportedPair)
+            }
+        }
+
+        fn _set_reserves(
+            &mut self,
+            token_in: AccountId,
+            reserve_in: Balance,
+            reserve_out: Balance,
+        ) {
+            if token_in == self.token_a {
+                self.reserve_a = reserve_in;
+                self.reserve_b = reserve_out;
+            } else {
+                self.reserve_b = reserve_in;
+                self.reserve_a = reserve_out;
+            }
+        }
+    }
+
+    impl SwapAdapter for SwapAdapterMockContract {
+        #[ink(message)]
+        fn swap(
+            &mut self,
+            token_in: AccountId,
+            token_out: AccountId,
+            amount_in: Balance,
+            min_amount_out: Balance,
+            to: AccountId,
+        ) -> Result<Balance, SwapAdapterError> {
+            let (reserve_in, reserve_out) = self._get_reserves(token_in, token_out)?;
+            let balance_in = PSP22Ref::balance_of(&token_in, self.env().account_id());
+            if balance_in < reserve_in + amount_in {
+                return Err(SwapAdapterError::InsufficientInputAmount);
+            }
+            let amount_out = _get_amount_out(amount_in, reserve_in, reserve_out)?;
+            if amount_out < min_amount_out {
+                return Err(SwapAdapterError::InsufficientOutputAmount);
+            }
+            self._set_reserves(token_in, reserve_in + amount_in, reserve_out - amount_out);
+            PSP22Ref::transfer(&token_out, to, amount_out, Vec::<u8>::new())?;
+            Ok(amount_out)
+        }
+
+        #[ink(message)]
+        fn get_amount_out(
+            &self,
+            token_in: AccountId,
+            token_out: AccountId,
+            amount_in: Balance,
+        ) -> Result<Balance, SwapAdapterError> {
+            let (reserve_in, reserve_out) = self._get_reserves(token_in, token_out)?;
+            _get_amount_out(amount_in, reserve_in, reserve_out)
+        }
+    }
+
+    impl PSP22Receiver for SwapAdapterMockContract {
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            _value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22ReceiverError> {
+            Ok(())
+        }
+    }
+
+    // reserve_in * reserve_out stays constant, no fee is taken
+    fn _get_amount_out(
+        amount_in: Balance,
+        reserve_in: Balance,
+        reserve_out: Balance,
+    ) -> Result<Balance, SwapAdapterError> {
+        if reserve_in == 0 || reserve_out == 0 {
+            return Err(SwapAdapterError::InsufficientLiquidity);
+        }
+        Ok(reserve_out * amount_in / (reserve_in + amount_in))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_lang as ink;
+
+        #[ink::test]
+        fn amount_out_keeps_product() {
+            let (reserve_in, reserve_out) = (1_000_000, 2_000_000);
+            let amount_in = 250_000;
+            let amount_out = _get_amount_out(amount_in, reserve_in, reserve_out).unwrap();
+            assert_eq!(amount_out, 400_000);
+            assert!(
+                (reserve_in + amount_in) * (reserve_out - amount_out) >= reserve_in * reserve_out
+            );
+        }
+
+        #[ink::test]
+        fn empty_pool_has_no_liquidity() {
+            assert_eq!(
+                _get_amount_out(1, 0, 1_000),
+                Err(SwapAdapterError::InsufficientLiquidity)
+            );
+        }
+    }
+}
@@ -4,29 +4,48 @@
 #[brush::contract]
 pub mod vault {
     use brush::{
-        contracts::{ownable::*, pausable::*, psp22::*, psp34::extensions::metadata::*, psp34::*},
+        contracts::{
+            ownable::*, pausable::*, psp22::*, psp34::extensions::metadata::*, psp34::*,
+            reentrancy_guard::*,
+        },
         modifiers,
+        traits::Flush,
     };
+    use ink_env::CallFlags;
     use ink_lang::codegen::EmitEvent;
     use ink_lang::codegen::Env;
-    use ink_prelude::string::ToString;
+    use ink_prelude::string::{String, ToString};
     use ink_prelude::vec::Vec;
     use ink_storage::traits::SpreadAllocate;
     use ink_storage::Mapping;
+    use scale::{Decode, Encode};
     use stable_coin_project::impls::collateralling::*;
     use stable_coin_project::impls::emitting::*;
     use stable_coin_project::impls::pausing::*;
     use stable_coin_project::impls::shares_profit_generating::*;
+    use stable_coin_project::impls::upgrading::*;
+    use stable_coin_project::math::rpow_e12;
+    use stable_coin_project::traits::flash_lending::*;
+    use stable_coin_project::traits::freezing::FreezingViewRef;
     use stable_coin_project::traits::oracling::OraclingRef;
     use stable_coin_project::traits::psp22_rated::*;
+    use stable_coin_project::traits::shares_profit_controlling::PControllingRef;
+    use stable_coin_project::traits::swap_adapter::*;
     use stable_coin_project::traits::vault::*;
 
-    // const U128MAX: u128 = 340282366920938463463374607431768211455;
+    const U128MAX: u128 = 340282366920938463463374607431768211455;
     const E6: u128 = 10_u128.pow(6);
     const E12: u128 = 10_u128.pow(12);
 
     const COLLATERAL_DECIMALS: u128 = 10_u128.pow(12);
+    // 1 - layout of the first upgradable vault
+    // 2 - swapping
+    // 3 - transfer epochs of vault approvals
+    // 4 - flash fee collateral
+    const STORAGE_VERSION: u32 = 4;
     const STABLE_DECIMALS: u128 = 10_u128.pow(6);
+    // swaps done by one leverage or deleverage, each one closes the gap to target ratio left by slippage of previous one
+    const MAX_LEVERAGE_STEPS: u8 = 4;
 
     #[ink(storage)]
     #[derive(
@@ -39,6 +58,8 @@ pub mod vault {
         CollaterallingStorage,
         EmittingStorage,
         SPGeneratingStorage,
+        ReentrancyGuardStorage,
+        UpgradingStorage,
     )]
     pub struct VaultContract {
         #[OwnableStorageField]
@@ -55,6 +76,10 @@ pub mod vault {
         emit: EmittingData,
         #[SPGeneratingStorageField]
         spgenerate: SPGeneratingData,
+        #[ReentrancyGuardStorageField]
+        guard: ReentrancyGuardData,
+        #[UpgradingStorageField]
+        upgrading: UpgradingData,
 
         // immutables
         pub maximum_minimum_collateral_coefficient_e6: u128,
@@ -66,6 +91,12 @@ pub mod vault {
         pub debt_by_id: Mapping<u128, Balance>,
         pub total_debt: Balance,
         pub next_id: u128,
+        pub operator_approvals: Mapping<(AccountId, AccountId, Option<u128>), bool>, // (owner, operator, vault_id), None vault_id means all vaults of owner
+        pub recipient_of: Mapping<AccountId, AccountId>, // receives collateral withdrawn and tokens borrowed from owners vaults
+        pub vault_count_of: Mapping<AccountId, u128>,    // number of vaults owned by account
+        pub vault_of_owner_by_index: Mapping<(AccountId, u128), u128>, // (owner, index) -> vault_id
+        pub index_of_vault: Mapping<u128, u128>,         // vault_id -> index in owners vaults
+        pub accepts_debt_vaults: Mapping<AccountId, bool>, // vaults with debt can be transfered only to accounts that accept them
 
         pub current_interest_coefficient_e12: u128, // the current interest coefficient (acmulated interest)
         pub last_interest_coefficient_by_id_e12: Mapping<u128, u128>, // the last interest coefficient (acumulated interest) used for vault with id
@@ -75,15 +106,47 @@ pub mod vault {
         pub oracle_address: AccountId,
         pub controller_address: AccountId, // controlling_contract
         pub liquidator_address: AccountId,
+        pub swap_adapter_address: AccountId, // swaps emited token and collateral for leverage and deleverage
 
         //// vault parameters
         pub current_interest_rate_e12: i128, // interest_rate_step_value_e12 * current_interest_step( which is stored in vault_controller)
         pub current_minimum_collateral_coefficient_e6: u128, // maximum_minimum_collaterall - collateral_step_value * current_collateral_step (shich is stored in vault_controller)
+        pub interest_rate_premium_e12: i128, // added on top of current_interest_rate_e12, specific to this vault type (collateral)
+        pub min_vault_debt: Balance, // vault debt must be 0 or at least min_vault_debt, smaller debts are not worth liquidating
+        pub max_total_debt: Balance, // debt ceiling for this collateral, compared with total_debt
+        pub max_emited_supply: Balance, // borrowing stops when total supply of emited token would exceed it, supply includes tokens minted outside vaults
+
+        //// flash loans of collateral
+        pub max_flash_loan: Balance,
+        pub flash_fee_e6: u128,
+
+        // fields appended after the first upgradable layout are kept in mappings, so loading storage of older versions,
+        // which has no entries for them, doesn't trap and missing entries read as defaults
+        // since storage version 2
+        pub swapping: Mapping<(), bool>, // true only during call to swap adapter, output of the swap is accepted only then
+        // since storage version 3
+        pub transfer_epoch_by_id: Mapping<u128, u32>, // incremented on each transfer of the vault
+        pub approval_epoch: Mapping<(AccountId, AccountId, u128), u32>, // (owner, operator, vault_id) -> transfer epoch of the vault when approval was set
+        // since storage version 4
+        pub flash_fee_collateral: Mapping<(), Balance>, // flash loan fees, collateral of no vault owned by treassury until collected
     }
     impl Ownable for VaultContract {} // owner can pause contract
+    impl Upgrading for VaultContract {} // owner can replace code, migrate moves storage to new layout
     impl Pausable for VaultContract {} // when paused borrowing is imposible
     impl Pausing for VaultContract {} // owner can pause and unpause
     impl PSP34 for VaultContract {} // PSP34 is prove of being vault_owner
+    impl PSP34Metadata for VaultContract {
+        // attributes describing vault position, the ones depending on interest and price are computed when read
+        #[ink(message)]
+        fn get_attribute(&self, id: Id, key: Vec<u8>) -> Option<Vec<u8>> {
+            match id {
+                Id::U128(vault_id) if self._owner_of(&id).is_some() => {
+                    self._vault_attribute(vault_id, &key)
+                }
+                _ => None,
+            }
+        }
+    }
     impl EmittingInternal for VaultContract {} // minting and burning emited_token
     impl Emitting for VaultContract {} // emited_amount() = minted - burned
     impl CollaterallingInternal for VaultContract {} // transfer in, transfer out
@@ -121,7 +184,10 @@ pub mod vault {
                     maximum_minimum_collateral_coefficient_e6;
                 instance.collateral_step_value_e6 = collateral_step_value_e6;
                 instance.interest_rate_step_value_e12 = interest_rate_step_value_e12;
+                instance.max_total_debt = U128MAX;
+                instance.max_emited_supply = U128MAX;
                 instance._init_with_owner(owner);
+                instance.upgrading.storage_version = STORAGE_VERSION;
             })
         }
 
@@ -133,25 +199,67 @@ pub mod vault {
             key: Vec<u8>,
             value: Vec<u8>,
         ) -> Result<(), VaultError> {
-            self._set_attribute(id, key, value)?;
+            PSP34MetadataInternal::_set_attribute(self, id, key, value);
             Ok(())
         }
     }
 
     impl PSP22Receiver for VaultContract {
         #[ink(message)]
+        #[modifiers(when_migrated)]
         fn before_received(
             &mut self,
-            _operator: AccountId,
-            _from: AccountId,
-            _value: Balance,
-            _data: Vec<u8>,
+            operator: AccountId,
+            from: AccountId,
+            value: Balance,
+            data: Vec<u8>,
         ) -> Result<(), PSP22ReceiverError> {
-            if self.env().caller() != self.collateral.collateral_token_address {
+            let token = self.env().caller();
+            let is_swap_output =
+                self.swapping.get(&()).unwrap_or(false) && from == self.swap_adapter_address;
+            // emited token is accepted only as output of deleverage swap
+            if token == self.emit.emited_token_address && is_swap_output {
+                return Ok(());
+            }
+            if token != self.collateral.collateral_token_address {
                 return Err(PSP22ReceiverError::TransferRejected(
                     "UnacceptedPsp22".to_string(),
                 ));
             }
+            // output of leverage swap is credited by the message that swaps
+            if is_swap_output {
+                return Ok(());
+            }
+            // collateral pulled by the vault itself is credited by the message that pulls it
+            if operator == self.env().account_id() {
+                return Ok(());
+            }
+            // transfer and call, data chooses the vault that is credited
+            let vault_id: u128 = match DepositTarget::decode(&mut &data[..]) {
+                Ok(DepositTarget::Vault(vault_id)) => {
+                    if self._owner_of(&Id::U128(vault_id)).is_none() {
+                        return Err(PSP22ReceiverError::TransferRejected(
+                            "OwnerUnexists".to_string(),
+                        ));
+                    }
+                    vault_id
+                }
+                Ok(DepositTarget::NewVault) => match self._create_vault(from) {
+                    Ok(vault_id) => vault_id,
+                    Err(_) => {
+                        return Err(PSP22ReceiverError::TransferRejected(
+                            "CouldntCreateVault".to_string(),
+                        ))
+                    }
+                },
+                Err(_) => {
+                    return Err(PSP22ReceiverError::TransferRejected(
+                        "UndirectedDeposit".to_string(),
+                    ))
+                }
+            };
+            self._receive_collateral(value);
+            self._credit_collateral(vault_id, value);
             Ok(())
         }
     }
@@ -159,25 +267,17 @@ pub mod vault {
     impl Vault for VaultContract {
         // mints a NFT to caller that represent vault
         #[ink(message)]
+        #[modifiers(when_migrated)]
         fn create_vault(&mut self) -> Result<(), VaultError> {
             ink_env::debug_println!("create_vault START");
-            let caller = self.env().caller();
-            let next_id = self.next_id;
-
-            self._mint_to(caller, Id::U128(next_id))?;
-            self.debt_by_id.insert(&next_id, &(0));
-            ink_env::debug_println!("create_vault debt: {}", self._get_debt_by_id(&next_id));
-            self.collateral_by_id.insert(&next_id, &(0));
-            self.last_interest_coefficient_by_id_e12
-                .insert(&next_id, &(self.current_interest_coefficient_e12));
-            ink_env::debug_println!("create_vault2 debt: {}", self._get_debt_by_id(&next_id));
-            self.next_id += 1;
+            self._create_vault(self.env().caller())?;
             ink_env::debug_println!("create_vault STOP");
             Ok(())
         }
 
         // burns a NFT from a caller that represent vault
         #[ink(message)]
+        #[modifiers(when_migrated)]
         fn destroy_vault(&mut self, vault_id: u128) -> Result<(), VaultError> {
             ink_env::debug_println!("destroy_vault START");
             let vault_owner: AccountId = match self._owner_of(&Id::U128(vault_id)) {
@@ -187,170 +287,379 @@ pub mod vault {
             if self.env().caller() != vault_owner {
                 return Err(VaultError::VaultOwnership);
             }
-            if self._get_debt_by_id(&vault_id) != 0 {
-                return Err(VaultError::HasDebt);
-            }
-            if self._get_collateral_by_id(&vault_id) != 0 {
-                return Err(VaultError::NotEmpty);
-            }
-            self._burn_from(vault_owner, Id::U128(vault_id))?;
+            self._destroy_vault(vault_id, vault_owner)?;
             ink_env::debug_println!("destroy_vault STOP");
             Ok(())
         }
 
         // deposit collateral to the callers vault
         #[ink(message)]
+        #[modifiers(when_migrated)]
         fn deposit_collateral(
             &mut self,
             vault_id: u128,
             amount: Balance,
         ) -> Result<(), VaultError> {
             ink_env::debug_println!("deposit_collateral START");
-            ink_env::debug_println!(
-                "deposit_collateral debt: {}",
-                self._get_debt_by_id(&vault_id)
-            );
-            let vault_owner: AccountId = self.owner_of(Id::U128(vault_id)).unwrap_or_default();
-            if self.env().caller() != vault_owner {
-                return Err(VaultError::VaultOwnership);
-            }
-
-            //transfer in and increase collateral
-            let collateral = self._get_collateral_by_id(&vault_id);
-            self._transfer_collateral_in(vault_owner, amount)?;
-            self.collateral_by_id
-                .insert(&vault_id, &(collateral + amount));
-
-            // /event
-            self._emit_deposit_event(vault_id, collateral);
-            ink_env::debug_println!(
-                "deposit_collateral2 debt: {}",
-                self._get_debt_by_id(&vault_id)
-            );
+            self._check_vault_operator(vault_id)?;
+            self._deposit_collateral(vault_id, self.env().caller(), amount)?;
             ink_env::debug_println!("deposit_collateral STOP");
             Ok(())
         }
 
         // updates vault debt and withdraws collateral if there is enought
         #[ink(message)]
+        #[modifiers(when_migrated)]
         fn withdraw_collateral(
             &mut self,
             vault_id: u128,
             amount: Balance,
         ) -> Result<(), VaultError> {
             ink_env::debug_println!("withdraw_collateral START");
-            ink_env::debug_println!(
-                "withdraw_collateral debt: {}",
-                self._get_debt_by_id(&vault_id)
-            );
-            let vault_owner: AccountId = self.owner_of(Id::U128(vault_id)).unwrap_or_default();
-            if self.env().caller() != vault_owner {
-                return Err(VaultError::VaultOwnership);
-            }
-
-            // check if there is enought collateral to withdraw
-            let vault_collateral = self._get_collateral_by_id(&vault_id);
-            if amount > vault_collateral {
-                return Err(VaultError::CollateralBelowMinimum);
-            }
-
+            let vault_owner: AccountId = self._check_vault_operator(vault_id)?;
+            let debt = self._update_vault_debt(vault_id)?;
             // check if after withdraw vault is not undercollaterized
-            ink_env::debug_println!(
-                "check_undercollateralize debt {}",
-                self._get_debt_by_id(&vault_id)
-            );
-            let vault_debt = self._update_vault_debt(vault_id)?;
-            let collateral_after = vault_collateral - amount;
-            ink_env::debug_println!("check_undercollateralize3 {}", vault_debt);
-            if vault_debt * self.current_minimum_collateral_coefficient_e6
-                > self._collateral_value_e6(collateral_after)
-            //TODO something is wrong here
-            {
+            if amount > self._max_withdrawable(self._get_collateral_by_id(&vault_id), debt) {
                 return Err(VaultError::CollateralBelowMinimum);
             }
-
-            // transfer out and decrease collateral
-            ink_env::debug_println!("transfer_out");
-            self.collateral_by_id.insert(&vault_id, &collateral_after);
-            ink_env::debug_println!("transfer_out2");
-            self._transfer_collateral_out(vault_owner, amount)?;
-
-            //event
-            ink_env::debug_println!("event");
-            self._emit_deposit_event(vault_id, collateral_after);
+            self._withdraw_collateral(vault_id, self._get_recipient(vault_owner), amount)?;
             ink_env::debug_println!("withdraw_collateral STOP");
-
             Ok(())
         }
 
         // updates vault and borrows tokens if possible
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[brush::modifiers(when_not_paused)]
         fn borrow_token(&mut self, vault_id: u128, amount: Balance) -> Result<(), VaultError> {
             ink_env::debug_println!("borrow_token START");
-            ink_env::debug_println!("amount: {}", amount);
-            ink_env::debug_println!("debt: {}", self._get_debt_by_id(&vault_id));
-            let vault_owner: AccountId = self.owner_of(Id::U128(vault_id)).unwrap_or_default();
-            if self.env().caller() != vault_owner {
-                return Err(VaultError::VaultOwnership);
-            }
-
-            // check if after borrow vault is not undercollaterized
-            let debt_ceiling: Balance = self._get_debt_ceiling(vault_id);
+            let vault_owner: AccountId = self._check_vault_operator(vault_id)?;
             let debt = self._update_vault_debt(vault_id)?;
-            if debt + amount > debt_ceiling {
+            // check if after borrow vault is not undercollaterized
+            if amount > self._max_borrowable(self._get_debt_ceiling(vault_id), debt) {
                 return Err(VaultError::CollateralBelowMinimum);
             }
+            self._borrow_token(
+                vault_id,
+                vault_owner,
+                amount,
+                self._get_recipient(vault_owner),
+            )?;
+            ink_env::debug_println!("borrow_token STOP");
+            Ok(())
+        }
 
-            // increase debt and borrow tokens
-            self.debt_by_id.insert(&vault_id, &(debt + amount));
-            PSP22RatedRef::add_account_debt(&self.emit.emited_token_address, vault_owner, amount)?;
-
-            self.total_debt += amount;
-            ink_env::debug_println!("amount: {}", amount);
-            self._mint_emited_token(vault_owner, amount)?;
-
-            //event
-            self._emit_borrow_event(vault_id, amount);
+        // updates debt and pays back up to amount of it with callers tokens, anyone can repay any vault
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn repay(&mut self, vault_id: u128, amount: Balance) -> Result<(), VaultError> {
+            let vault_owner: AccountId = match self._owner_of(&Id::U128(vault_id)) {
+                Some(v) => v,
+                None => return Err(VaultError::OwnerUnexists),
+            };
+            self._repay(vault_id, vault_owner, self.env().caller(), amount)?;
             Ok(())
         }
 
-        // updates debt and pay back some debt
+        // creates vault, deposits collateral and borrows in one call, returns (vault_id, collateral, debt)
         #[ink(message)]
-        fn pay_back_token(&mut self, vault_id: u128, amount: Balance) -> Result<(), VaultError> {
-            if self.env().caller() != self.liquidator_address {
-                return Err(VaultError::Liquidator);
+        #[modifiers(when_migrated)]
+        #[brush::modifiers(when_not_paused)]
+        fn open_and_borrow(
+            &mut self,
+            collateral: Balance,
+            debt: Balance,
+        ) -> Result<(u128, Balance, Balance), VaultError> {
+            let caller = self.env().caller();
+            let vault_id = self._create_vault(caller)?;
+            self._deposit_collateral(vault_id, caller, collateral)?;
+            if debt != 0 {
+                self._borrow_token(vault_id, caller, debt, self._get_recipient(caller))?;
             }
-            let vault_owner: AccountId = self.owner_of(Id::U128(vault_id)).unwrap_or_default();
+            self._check_collateralization(vault_id)?;
+            Ok((
+                vault_id,
+                self._get_collateral_by_id(&vault_id),
+                self._get_debt_by_id(&vault_id),
+            ))
+        }
+
+        // deposits (+) or withdraws (-) collateral and borrows (+) or repays (-) debt in one call, returns (collateral, debt)
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn adjust_vault(
+            &mut self,
+            vault_id: u128,
+            collateral_delta: i128,
+            debt_delta: i128,
+        ) -> Result<(Balance, Balance), VaultError> {
+            let caller = self.env().caller();
+            let vault_owner: AccountId = self._check_vault_operator(vault_id)?;
+            // collateral comes in and debt goes out first, so the vault is never less secured than needed
+            if collateral_delta > 0 {
+                self._deposit_collateral(vault_id, caller, collateral_delta as u128)?;
+            }
+            if debt_delta < 0 {
+                self._repay(vault_id, vault_owner, caller, debt_delta.unsigned_abs())?;
+            }
+            if debt_delta > 0 {
+                if self.paused() {
+                    return Err(VaultError::PausableError(PausableError::Paused));
+                }
+                self._borrow_token(
+                    vault_id,
+                    vault_owner,
+                    debt_delta as u128,
+                    self._get_recipient(vault_owner),
+                )?;
+            }
+            if collateral_delta < 0 {
+                self._withdraw_collateral(
+                    vault_id,
+                    self._get_recipient(vault_owner),
+                    collateral_delta.unsigned_abs(),
+                )?;
+            }
+            self._check_collateralization(vault_id)?;
+            Ok((
+                self._get_collateral_by_id(&vault_id),
+                self._get_debt_by_id(&vault_id),
+            ))
+        }
+
+        // repays whole debt with callers tokens, withdraws all collateral and burns the vault, returns (withdrawn collateral, repaid debt)
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn repay_all_and_close(
+            &mut self,
+            vault_id: u128,
+        ) -> Result<(Balance, Balance), VaultError> {
+            let vault_owner: AccountId = match self._owner_of(&Id::U128(vault_id)) {
+                Some(v) => v,
+                None => return Err(VaultError::OwnerUnexists),
+            };
             if self.env().caller() != vault_owner {
                 return Err(VaultError::VaultOwnership);
             }
             let debt = self._update_vault_debt(vault_id)?;
-            if amount >= debt {
-                self._burn_emited_token(vault_owner, debt)?;
-                self.debt_by_id.insert(&vault_id, &(0));
-                PSP22RatedRef::sub_account_debt(
-                    &self.emit.emited_token_address,
-                    vault_owner,
-                    debt,
+            let repaid = self._repay(vault_id, vault_owner, vault_owner, debt)?;
+            let collateral = self._get_collateral_by_id(&vault_id);
+            if collateral != 0 {
+                self._withdraw_collateral(vault_id, self._get_recipient(vault_owner), collateral)?;
+            }
+            // vault is cleared by _destroy_vault, so withdrawn and repaid amounts are returned
+            self._destroy_vault(vault_id, vault_owner)?;
+            Ok((collateral, repaid))
+        }
+
+        // moves collateral and debt of from_id vault to into_id vault and burns from_id, both must have the same owner
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn merge_vaults(&mut self, into_id: u128, from_id: u128) -> Result<(), VaultError> {
+            if into_id == from_id {
+                return Err(VaultError::SameVault);
+            }
+            let vault_owner: AccountId = self._check_vault_operator(into_id)?;
+            if self._check_vault_operator(from_id)? != vault_owner {
+                return Err(VaultError::VaultOwnership);
+            }
+            // settle interest of both vaults at current coefficient
+            let into_debt = self._update_vault_debt(into_id)?;
+            let from_debt = self._update_vault_debt(from_id)?;
+            let into_collateral = self._get_collateral_by_id(&into_id);
+            let from_collateral = self._get_collateral_by_id(&from_id);
+
+            // debt stays with the same owner so account_debt and total_debt do not change
+            self.collateral_by_id
+                .insert(&into_id, &(into_collateral + from_collateral));
+            self.debt_by_id.insert(&into_id, &(into_debt + from_debt));
+            self.collateral_by_id.insert(&from_id, &0);
+            self.debt_by_id.insert(&from_id, &0);
+            self._check_collateralization(into_id)?;
+            self._destroy_vault(from_id, vault_owner)?;
+
+            self._emit_vaults_merged_event(into_id, from_id);
+            Ok(())
+        }
+
+        // moves collateral and debt from vault to a newly minted vault of the same owner, returns new vault_id
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn split_vault(
+            &mut self,
+            vault_id: u128,
+            collateral: Balance,
+            debt: Balance,
+        ) -> Result<u128, VaultError> {
+            let vault_owner: AccountId = self._check_vault_operator(vault_id)?;
+            // settle interest so both vaults start from current coefficient
+            let vault_debt = self._update_vault_debt(vault_id)?;
+            let vault_collateral = self._get_collateral_by_id(&vault_id);
+            if collateral > vault_collateral {
+                return Err(VaultError::CollateralBelowMinimum);
+            }
+            if debt > vault_debt {
+                return Err(VaultError::DebtUnexists);
+            }
+            let debt_after = vault_debt - debt;
+            if (debt_after != 0 && debt_after < self.min_vault_debt)
+                || (debt != 0 && debt < self.min_vault_debt)
+            {
+                return Err(VaultError::DebtBelowMinimum);
+            }
+
+            // debt stays with the same owner so account_debt and total_debt do not change
+            let new_vault_id = self._create_vault(vault_owner)?;
+            self.collateral_by_id
+                .insert(&vault_id, &(vault_collateral - collateral));
+            self.debt_by_id.insert(&vault_id, &debt_after);
+            self.collateral_by_id.insert(&new_vault_id, &collateral);
+            self.debt_by_id.insert(&new_vault_id, &debt);
+            self._check_collateralization(vault_id)?;
+            self._check_collateralization(new_vault_id)?;
+
+            self._emit_vault_split_event(vault_id, new_vault_id, collateral, debt);
+            Ok(new_vault_id)
+        }
+
+        // borrows emited token, swaps it to collateral and deposits it, so collateral ratio falls to target_ratio_e6
+        // borrowed amount is valued by oracle, swap slippage leaves ratio slightly below target and ends the loop
+        // swap that gives more than oracle value leaves ratio above target, so it is repeated up to MAX_LEVERAGE_STEPS times
+        // min_collateral_out bounds sum of all swaps, returns (borrowed, deposited)
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[brush::modifiers(when_not_paused)]
+        fn leverage(
+            &mut self,
+            vault_id: u128,
+            target_ratio_e6: u128,
+            min_collateral_out: Balance,
+        ) -> Result<(Balance, Balance), VaultError> {
+            let vault_owner: AccountId = self._check_vault_operator(vault_id)?;
+            if target_ratio_e6 < self.current_minimum_collateral_coefficient_e6
+                || target_ratio_e6 <= E6
+            {
+                return Err(VaultError::CollateralBelowMinimum);
+            }
+            let mut total_borrowed: Balance = 0;
+            let mut total_deposited: Balance = 0;
+            for _ in 0..MAX_LEVERAGE_STEPS {
+                let debt = self._update_vault_debt(vault_id)?;
+                let collateral_value = self._vault_collateral_value_e6(vault_id);
+                if collateral_value * E6 <= target_ratio_e6 * debt {
+                    break;
+                }
+                // (collateral_value + borrowed) / (debt + borrowed) = target_ratio, emited token is worth 1 USD
+                let borrowed =
+                    (collateral_value * E6 - target_ratio_e6 * debt) / (target_ratio_e6 - E6);
+                if borrowed == 0 {
+                    break;
+                }
+
+                // tokens are minted straight to swap adapter, vault is checked after collateral is deposited
+                self._borrow_token(vault_id, vault_owner, borrowed, self.swap_adapter_address)?;
+                let deposited = self._swap(
+                    self.emit.emited_token_address,
+                    self.collateral.collateral_token_address,
+                    borrowed,
+                    0,
                 )?;
-                self.total_debt -= debt;
-                self._emit_pay_back_event(vault_id, debt);
-            } else {
-                self._burn_emited_token(vault_owner, amount)?;
-                self.debt_by_id.insert(&vault_id, &(debt - amount));
-                PSP22RatedRef::sub_account_debt(
+                self._receive_collateral(deposited);
+                self._credit_collateral(vault_id, deposited);
+                total_borrowed += borrowed;
+                total_deposited += deposited;
+            }
+            if total_borrowed == 0 {
+                return Ok((0, 0));
+            }
+            if total_deposited < min_collateral_out {
+                return Err(VaultError::SwapAdapterError(
+                    SwapAdapterError::InsufficientOutputAmount,
+                ));
+            }
+            self._check_collateralization(vault_id)?;
+            Ok((total_borrowed, total_deposited))
+        }
+
+        // withdraws collateral, swaps it to emited token and repays debt, so collateral ratio rises to target_ratio_e6
+        // swap slippage leaves ratio below target, so it is repeated up to MAX_LEVERAGE_STEPS times
+        // min_stable_out bounds sum of all swaps, tokens left after whole debt is repaid are sent to owners recipient
+        // returns (withdrawn, repaid)
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn deleverage(
+            &mut self,
+            vault_id: u128,
+            target_ratio_e6: u128,
+            min_stable_out: Balance,
+        ) -> Result<(Balance, Balance), VaultError> {
+            let vault_owner: AccountId = self._check_vault_operator(vault_id)?;
+            if target_ratio_e6 <= E6 {
+                return Err(VaultError::CollateralBelowMinimum);
+            }
+            let collateral_price_e6 = OraclingRef::get_azero_usd_price_e6(&self.oracle_address);
+            if collateral_price_e6 == 0 {
+                return Err(VaultError::CollateralBelowMinimum);
+            }
+            let mut total_withdrawn: Balance = 0;
+            let mut total_received: Balance = 0;
+            let mut total_repaid: Balance = 0;
+            for _ in 0..MAX_LEVERAGE_STEPS {
+                let debt = self._update_vault_debt(vault_id)?;
+                let collateral_value = self._vault_collateral_value_e6(vault_id);
+                if debt == 0 || target_ratio_e6 * debt <= collateral_value * E6 {
+                    break;
+                }
+                // (collateral_value - sold) / (debt - sold) = target_ratio, at most whole debt is repaid
+                let mut sold_value =
+                    (target_ratio_e6 * debt - collateral_value * E6) / (target_ratio_e6 - E6);
+                if sold_value > debt {
+                    sold_value = debt;
+                }
+                let mut withdrawn = sold_value * COLLATERAL_DECIMALS / collateral_price_e6;
+                let collateral = self._get_collateral_by_id(&vault_id);
+                if withdrawn > collateral {
+                    withdrawn = collateral;
+                }
+                if withdrawn == 0 {
+                    break;
+                }
+
+                self._withdraw_collateral(vault_id, self.swap_adapter_address, withdrawn)?;
+                let received = self._swap(
+                    self.collateral.collateral_token_address,
+                    self.emit.emited_token_address,
+                    withdrawn,
+                    0,
+                )?;
+                total_withdrawn += withdrawn;
+                total_received += received;
+                total_repaid +=
+                    self._repay(vault_id, vault_owner, self.env().account_id(), received)?;
+            }
+            if total_withdrawn == 0 {
+                return Ok((0, 0));
+            }
+            if total_received < min_stable_out {
+                return Err(VaultError::SwapAdapterError(
+                    SwapAdapterError::InsufficientOutputAmount,
+                ));
+            }
+            if total_received > total_repaid {
+                PSP22Ref::transfer(
                     &self.emit.emited_token_address,
-                    vault_owner,
-                    amount,
+                    self._get_recipient(vault_owner),
+                    total_received - total_repaid,
+                    Vec::<u8>::new(),
                 )?;
-                self.total_debt -= amount;
-                self._emit_pay_back_event(vault_id, amount);
             }
-            Ok(())
+            self._check_collateralization(vault_id)?;
+            Ok((total_withdrawn, total_repaid))
         }
+
         // if vault has not enough collateral, callers pays back whole debt
         #[ink(message)]
+        #[modifiers(when_migrated)]
         fn buy_risky_vault(&mut self, vault_id: u128) -> Result<(), VaultError> {
             let caller = self.env().caller();
             let vault_owner: AccountId = self.owner_of(Id::U128(vault_id)).unwrap_or_default();
@@ -369,6 +678,7 @@ pub mod vault {
             self.total_debt -= debt;
 
             // transferting PSP34 ownership
+            self._before_token_transfer(Some(&vault_owner), Some(&caller), &Id::U128(vault_id))?;
             self._remove_token(&vault_owner, &Id::U128(vault_id))?;
             self._do_safe_transfer_check(
                 &caller,
@@ -380,13 +690,82 @@ pub mod vault {
             self._add_token(&caller, &Id::U128(vault_id))?;
 
             // events
-            self._emit_pay_back_event(vault_id, debt);
+            self._emit_pay_back_event(vault_id, caller, debt);
             self._emit_transfer_event(Some(vault_owner), Some(caller), Id::U128(vault_id));
 
             Ok(())
         }
 
+        // sends collateral collected as flash loan fees to treassury of shares profit controller, returns sent amount
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn collect_flash_fees(&mut self) -> Result<Balance, VaultError> {
+            let amount = self.flash_fee_collateral.get(&()).unwrap_or(0);
+            if amount == 0 {
+                return Ok(0);
+            }
+            let treassury = PControllingRef::get_treassury_address(
+                &self.spgenerate.shares_profit_controller_address,
+            );
+            self.flash_fee_collateral.insert(&(), &0);
+            self._transfer_collateral_out(treassury, amount)?;
+            self.env()
+                .emit_event(FlashFeesCollected { treassury, amount });
+            Ok(amount)
+        }
+
+        // allows operator to deposit, withdraw, borrow and pay back for one or (None) all callers vaults
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn approve_operator(
+            &mut self,
+            operator: AccountId,
+            vault_id: Option<u128>,
+            approved: bool,
+        ) -> Result<(), VaultError> {
+            let caller = self.env().caller();
+            if operator == caller {
+                return Err(VaultError::PSP34Error(PSP34Error::SelfApprove));
+            }
+            if let Some(id) = vault_id {
+                if self._owner_of(&Id::U128(id)) != Some(caller) {
+                    return Err(VaultError::VaultOwnership);
+                }
+            }
+            self.operator_approvals
+                .insert(&(caller, operator, vault_id), &approved);
+            if let Some(id) = vault_id {
+                self.approval_epoch.insert(
+                    &(caller, operator, id),
+                    &self.transfer_epoch_by_id.get(&id).unwrap_or(0),
+                );
+            }
+            self._emit_operator_approval_event(caller, operator, vault_id, approved);
+            Ok(())
+        }
+
+        // sets where withdrawn collateral and borrowed tokens of callers vaults are sent
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn set_recipient(&mut self, recipient: AccountId) -> Result<(), VaultError> {
+            let caller = self.env().caller();
+            self.recipient_of.insert(&caller, &recipient);
+            self._emit_recipient_set_event(caller, recipient);
+            Ok(())
+        }
+
+        // allows or forbids transfers of vaults with debt to caller
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn set_debt_vaults_accepted(&mut self, accepted: bool) -> Result<(), VaultError> {
+            let caller = self.env().caller();
+            self.accepts_debt_vaults.insert(&caller, &accepted);
+            self._emit_debt_vaults_accepted_event(caller, accepted);
+            Ok(())
+        }
+
         #[ink(message)]
+        #[modifiers(when_migrated)]
         fn be_controlled(
             &mut self,
             current_interest_rate_step: i16,
@@ -398,6 +777,8 @@ pub mod vault {
                 return Err(VaultError::VaultController);
             }
 
+            // accumulate interest with the old rate before it changes
+            self._update_current_interest_coefficient_e12();
             self.current_interest_rate_e12 =
                 current_interest_rate_step as i128 * self.interest_rate_step_value_e12;
 
@@ -408,6 +789,7 @@ pub mod vault {
         }
 
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_owner)]
         fn set_vault_controller_address(
             &mut self,
@@ -418,6 +800,7 @@ pub mod vault {
         }
 
         #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_owner)]
         fn set_oracle_address(&mut self, new_oracle_address: AccountId) -> Result<(), VaultError> {
             self.oracle_address = new_oracle_address;
@@ -425,6 +808,18 @@ pub mod vault {
         }
 
         #[ink(message)]
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_swap_adapter_address(
+            &mut self,
+            new_swap_adapter_address: AccountId,
+        ) -> Result<(), VaultError> {
+            self.swap_adapter_address = new_swap_adapter_address;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
         #[modifiers(only_owner)]
         fn set_liquidator_address(
             &mut self,
@@ -433,49 +828,346 @@ pub mod vault {
             self.liquidator_address = new_liquidator_address;
             Ok(())
         }
-    }
 
-    impl VaultView for VaultContract {
         #[ink(message)]
-        fn get_next_id(&mut self) -> u128 {
-            self.next_id
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_interest_rate_premium_e12(
+            &mut self,
+            new_interest_rate_premium_e12: i128,
+        ) -> Result<(), VaultError> {
+            // accumulate interest with the old premium before it changes
+            self._update_current_interest_coefficient_e12();
+            self.interest_rate_premium_e12 = new_interest_rate_premium_e12;
+            Ok(())
         }
-        // return total debt
+
         #[ink(message)]
-        fn get_total_debt(&self) -> Balance {
-            self.total_debt
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_min_vault_debt(&mut self, new_min_vault_debt: Balance) -> Result<(), VaultError> {
+            self.min_vault_debt = new_min_vault_debt;
+            Ok(())
         }
 
-        // returns cault collateral and debt
         #[ink(message)]
-        fn get_vault_details(&self, vault_id: u128) -> (Balance, Balance) {
-            (
-                self._get_collateral_by_id(&vault_id),
-                self._get_debt_by_id(&vault_id)
-                    * self._get_last_interest_coefficient_by_id_e12(&vault_id)
-                    / self._get_current_interest_coefficient_e12(),
-            )
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_max_total_debt(&mut self, new_max_total_debt: Balance) -> Result<(), VaultError> {
+            self.max_total_debt = new_max_total_debt;
+            Ok(())
         }
 
-        // returns maximum debt for a vault
         #[ink(message)]
-        fn get_debt_ceiling(&self, vault_id: u128) -> Balance {
-            self._get_debt_ceiling(vault_id)
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_max_emited_supply(
+            &mut self,
+            new_max_emited_supply: Balance,
+        ) -> Result<(), VaultError> {
+            self.max_emited_supply = new_max_emited_supply;
+            Ok(())
         }
+    }
 
+    impl FlashLending for VaultContract {
+        // lends collateral held by the vault to receiver for one call, amount + fee is pulled back after it
+        // lent collateral belongs to vaults of users, it is safe only because the whole transaction reverts unless it is returned
+        // disabled until owner sets max_flash_loan
         #[ink(message)]
-        fn get_vault_controller_address(&self) -> AccountId {
-            self.controller_address
+        #[modifiers(when_migrated)]
+        #[modifiers(non_reentrant)]
+        fn flash_loan(
+            &mut self,
+            receiver: AccountId,
+            amount: Balance,
+            data: Vec<u8>,
+        ) -> Result<(), FlashLendingError> {
+            if amount > self._max_flash_loan() {
+                return Err(FlashLendingError::AmountAboveMaximum);
+            }
+            let initiator = self.env().caller();
+            let fee = self._flash_fee(amount);
+            self._transfer_collateral_out(receiver, amount)?;
+
+            // receiver has to approve vault to take back amount + fee
+            self.flush();
+            let result = FlashBorrowerRef::on_flash_loan_builder(
+                &receiver,
+                initiator,
+                self.collateral.collateral_token_address,
+                amount,
+                fee,
+                data,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire();
+            self.load();
+            match result {
+                Ok(result) => result?,
+                Err(_) => return Err(FlashLendingError::CallbackFailed),
+            }
+
+            // fee is paid in collateral, so it is kept for treassury instead of being booked as emited token profit
+            self._transfer_collateral_in(receiver, amount + fee)?;
+            self.flash_fee_collateral.insert(
+                &(),
+                &(self.flash_fee_collateral.get(&()).unwrap_or(0) + fee),
+            );
+
+            self.env().emit_event(FlashLoan {
+                initiator,
+                receiver,
+                amount,
+                fee,
+            });
+            Ok(())
         }
 
         #[ink(message)]
-        fn get_oracle_address(&self) -> AccountId {
-            self.oracle_address
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_max_flash_loan(
+            &mut self,
+            new_max_flash_loan: Balance,
+        ) -> Result<(), FlashLendingError> {
+            self.max_flash_loan = new_max_flash_loan;
+            Ok(())
         }
 
         #[ink(message)]
-        fn get_liquidator_address(&self) -> AccountId {
-            self.liquidator_address
+        #[modifiers(when_migrated)]
+        #[modifiers(only_owner)]
+        fn set_flash_fee_e6(&mut self, new_flash_fee_e6: u128) -> Result<(), FlashLendingError> {
+            if new_flash_fee_e6 > E6 {
+                return Err(FlashLendingError::FeeAboveOne);
+            }
+            self.flash_fee_e6 = new_flash_fee_e6;
+            Ok(())
+        }
+    }
+
+    impl FlashLendingView for VaultContract {
+        // limited by max_flash_loan and collateral held by the vault
+        #[ink(message)]
+        fn max_flash_loan(&self) -> Balance {
+            self._max_flash_loan()
+        }
+
+        #[ink(message)]
+        fn flash_fee(&self, amount: Balance) -> Balance {
+            self._flash_fee(amount)
+        }
+
+        #[ink(message)]
+        fn flash_fee_e6(&self) -> u128 {
+            self.flash_fee_e6
+        }
+    }
+
+    impl VaultContract {
+        fn _max_flash_loan(&self) -> Balance {
+            let collateral_amount = self.collateral.collateral_amount;
+            if self.max_flash_loan < collateral_amount {
+                self.max_flash_loan
+            } else {
+                collateral_amount
+            }
+        }
+
+        // fee is rounded up
+        fn _flash_fee(&self, amount: Balance) -> Balance {
+            (amount * self.flash_fee_e6 + E6 - 1) / E6
+        }
+    }
+
+    impl VaultView for VaultContract {
+        #[ink(message)]
+        fn get_next_id(&mut self) -> u128 {
+            self.next_id
+        }
+        // return total debt
+        #[ink(message)]
+        fn get_total_debt(&self) -> Balance {
+            self.total_debt
+        }
+
+        // returns cault collateral and debt
+        #[ink(message)]
+        fn get_vault_details(&self, vault_id: u128) -> (Balance, Balance) {
+            (
+                self._get_collateral_by_id(&vault_id),
+                self._get_vault_debt_at(vault_id, self.env().block_timestamp()),
+            )
+        }
+
+        // returns maximum debt for a vault
+        #[ink(message)]
+        fn get_debt_ceiling(&self, vault_id: u128) -> Balance {
+            self._get_debt_ceiling(vault_id)
+        }
+
+        #[ink(message)]
+        fn get_vault_controller_address(&self) -> AccountId {
+            self.controller_address
+        }
+
+        #[ink(message)]
+        fn get_oracle_address(&self) -> AccountId {
+            self.oracle_address
+        }
+
+        #[ink(message)]
+        fn get_liquidator_address(&self) -> AccountId {
+            self.liquidator_address
+        }
+
+        #[ink(message)]
+        fn get_swap_adapter_address(&self) -> AccountId {
+            self.swap_adapter_address
+        }
+
+        #[ink(message)]
+        fn get_interest_rate_premium_e12(&self) -> i128 {
+            self.interest_rate_premium_e12
+        }
+
+        // returns rate used to accumulate debt: controller base rate + vault type premium
+        #[ink(message)]
+        fn get_current_interest_rate_e12(&self) -> i128 {
+            self._get_interest_rate_e12()
+        }
+
+        // returns debt the vault will have at timestamp if rates stay unchanged
+        #[ink(message)]
+        fn get_vault_debt_at(&self, vault_id: u128, timestamp: Timestamp) -> Balance {
+            self._get_vault_debt_at(vault_id, timestamp)
+        }
+
+        #[ink(message)]
+        fn is_operator(
+            &self,
+            owner: AccountId,
+            operator: AccountId,
+            vault_id: Option<u128>,
+        ) -> bool {
+            self._is_operator(owner, operator, vault_id)
+        }
+
+        #[ink(message)]
+        fn get_recipient(&self, owner: AccountId) -> AccountId {
+            self._get_recipient(owner)
+        }
+
+        #[ink(message)]
+        fn accepts_debt_vaults(&self, account: AccountId) -> bool {
+            self.accepts_debt_vaults.get(&account).unwrap_or(false)
+        }
+
+        // returns up to limit vault ids of owner starting from index start
+        #[ink(message)]
+        fn vaults_of(&self, owner: AccountId, start: u128, limit: u128) -> Vec<u128> {
+            let count = self.vault_count_of.get(&owner).unwrap_or(0);
+            let mut vaults = Vec::new();
+            let mut index = start;
+            while index < count && index < start.saturating_add(limit) {
+                if let Some(vault_id) = self.vault_of_owner_by_index.get(&(owner, index)) {
+                    vaults.push(vault_id);
+                }
+                index += 1;
+            }
+            vaults
+        }
+
+        #[ink(message)]
+        fn vault_info(&self, vault_id: u128) -> Option<VaultInfo> {
+            let owner = self._owner_of(&Id::U128(vault_id))?;
+            let collateral = self._get_collateral_by_id(&vault_id);
+            let debt = self._get_vault_debt_at(vault_id, self.env().block_timestamp());
+            let collateral_value_e6 = self._collateral_value_e6(collateral);
+            let debt_ceiling = self._get_debt_ceiling(vault_id);
+            Some(VaultInfo {
+                owner,
+                collateral,
+                debt,
+                collateral_value_e6,
+                collateral_ratio_e6: self._collateral_ratio_e6(collateral_value_e6, debt),
+                debt_ceiling,
+                liquidation_price_e6: self._liquidation_price_e6(collateral, debt),
+                health_factor_e6: self._health_factor_e6(debt_ceiling, debt),
+            })
+        }
+
+        // debt ceiling / debt with accrued interest, vault can be bought when it is below E6
+        #[ink(message)]
+        fn get_health_factor_e6(&self, vault_id: u128) -> u128 {
+            let debt = self._get_vault_debt_at(vault_id, self.env().block_timestamp());
+            self._health_factor_e6(self._get_debt_ceiling(vault_id), debt)
+        }
+
+        // collateral price at which vault becomes risky
+        #[ink(message)]
+        fn get_liquidation_price_e6(&self, vault_id: u128) -> u128 {
+            let debt = self._get_vault_debt_at(vault_id, self.env().block_timestamp());
+            self._liquidation_price_e6(self._get_collateral_by_id(&vault_id), debt)
+        }
+
+        // amount borrow_token accepts now, debt limits of the contract are not included
+        #[ink(message)]
+        fn get_max_borrowable(&self, vault_id: u128) -> Balance {
+            let debt = self._get_vault_debt_at(vault_id, self.env().block_timestamp());
+            self._max_borrowable(self._get_debt_ceiling(vault_id), debt)
+        }
+
+        // amount withdraw_collateral accepts now
+        #[ink(message)]
+        fn get_max_withdrawable(&self, vault_id: u128) -> Balance {
+            let debt = self._get_vault_debt_at(vault_id, self.env().block_timestamp());
+            self._max_withdrawable(self._get_collateral_by_id(&vault_id), debt)
+        }
+
+        // returns ids of vaults in [start, start + limit) with debt above debt ceiling
+        #[ink(message)]
+        fn risky_vaults(&self, start: u128, limit: u128) -> Vec<u128> {
+            let timestamp = self.env().block_timestamp();
+            let end = if start.saturating_add(limit) < self.next_id {
+                start + limit
+            } else {
+                self.next_id
+            };
+            let mut vaults = Vec::new();
+            let mut vault_id = start;
+            while vault_id < end {
+                if self._owner_of(&Id::U128(vault_id)).is_some() {
+                    if self._get_vault_debt_at(vault_id, timestamp)
+                        > self._get_debt_ceiling(vault_id)
+                    {
+                        vaults.push(vault_id);
+                    }
+                }
+                vault_id += 1;
+            }
+            vaults
+        }
+
+        #[ink(message)]
+        fn get_min_vault_debt(&self) -> Balance {
+            self.min_vault_debt
+        }
+
+        #[ink(message)]
+        fn get_max_total_debt(&self) -> Balance {
+            self.max_total_debt
+        }
+
+        #[ink(message)]
+        fn get_max_emited_supply(&self) -> Balance {
+            self.max_emited_supply
+        }
+
+        #[ink(message)]
+        fn get_flash_fee_collateral(&self) -> Balance {
+            self.flash_fee_collateral.get(&()).unwrap_or(0)
         }
     }
     impl VaultContractCheck for VaultContract {}
@@ -502,8 +1194,62 @@ pub mod vault {
     pub struct PayBack {
         #[ink(topic)]
         vault_id: u128,
+        #[ink(topic)]
+        payer: AccountId,
         pay_backed: Balance,
     }
+    #[ink(event)]
+    pub struct VaultsMerged {
+        #[ink(topic)]
+        into_id: u128,
+        #[ink(topic)]
+        from_id: u128,
+    }
+    #[ink(event)]
+    pub struct VaultSplit {
+        #[ink(topic)]
+        vault_id: u128,
+        #[ink(topic)]
+        new_vault_id: u128,
+        collateral: Balance,
+        debt: Balance,
+    }
+    #[ink(event)]
+    pub struct FlashLoan {
+        #[ink(topic)]
+        initiator: AccountId,
+        #[ink(topic)]
+        receiver: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
+    #[ink(event)]
+    pub struct FlashFeesCollected {
+        #[ink(topic)]
+        treassury: AccountId,
+        amount: Balance,
+    }
+    #[ink(event)]
+    pub struct OperatorApproval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        operator: AccountId,
+        vault_id: Option<u128>,
+        approved: bool,
+    }
+    #[ink(event)]
+    pub struct RecipientSet {
+        #[ink(topic)]
+        owner: AccountId,
+        recipient: AccountId,
+    }
+    #[ink(event)]
+    pub struct DebtVaultsAccepted {
+        #[ink(topic)]
+        account: AccountId,
+        accepted: bool,
+    }
 
     impl VaultInternal for VaultContract {
         fn _emit_deposit_event(&self, _vault_id: u128, _current_collateral: Balance) {
@@ -527,13 +1273,440 @@ pub mod vault {
             });
         }
 
-        fn _emit_pay_back_event(&self, _vault_id: u128, _pay_backed: Balance) {
+        fn _emit_pay_back_event(&self, _vault_id: u128, _payer: AccountId, _pay_backed: Balance) {
             self.env().emit_event(PayBack {
                 vault_id: _vault_id,
+                payer: _payer,
                 pay_backed: _pay_backed,
             });
         }
 
+        fn _emit_operator_approval_event(
+            &self,
+            _owner: AccountId,
+            _operator: AccountId,
+            _vault_id: Option<u128>,
+            _approved: bool,
+        ) {
+            self.env().emit_event(OperatorApproval {
+                owner: _owner,
+                operator: _operator,
+                vault_id: _vault_id,
+                approved: _approved,
+            });
+        }
+
+        fn _emit_recipient_set_event(&self, _owner: AccountId, _recipient: AccountId) {
+            self.env().emit_event(RecipientSet {
+                owner: _owner,
+                recipient: _recipient,
+            });
+        }
+
+        fn _emit_vaults_merged_event(&self, _into_id: u128, _from_id: u128) {
+            self.env().emit_event(VaultsMerged {
+                into_id: _into_id,
+                from_id: _from_id,
+            });
+        }
+
+        fn _emit_vault_split_event(
+            &self,
+            _vault_id: u128,
+            _new_vault_id: u128,
+            _collateral: Balance,
+            _debt: Balance,
+        ) {
+            self.env().emit_event(VaultSplit {
+                vault_id: _vault_id,
+                new_vault_id: _new_vault_id,
+                collateral: _collateral,
+                debt: _debt,
+            });
+        }
+
+        fn _emit_debt_vaults_accepted_event(&self, _account: AccountId, _accepted: bool) {
+            self.env().emit_event(DebtVaultsAccepted {
+                account: _account,
+                accepted: _accepted,
+            });
+        }
+
+        // mints vault NFT to owner and initializes its state, returns vault_id
+        fn _create_vault(&mut self, owner: AccountId) -> Result<u128, VaultError> {
+            let next_id = self.next_id;
+            self._mint_to(owner, Id::U128(next_id))?;
+            self.debt_by_id.insert(&next_id, &(0));
+            self.collateral_by_id.insert(&next_id, &(0));
+            self.last_interest_coefficient_by_id_e12
+                .insert(&next_id, &(self.current_interest_coefficient_e12));
+            self.next_id += 1;
+            self._set_vault_attribute(
+                next_id,
+                "collateral_token",
+                self.collateral.collateral_token_address.encode(),
+            );
+            self._set_vault_attribute(
+                next_id,
+                "created_at",
+                self.env().block_timestamp().to_string().into_bytes(),
+            );
+            Ok(next_id)
+        }
+
+        // burns vault NFT, vault must be empty
+        fn _destroy_vault(
+            &mut self,
+            vault_id: u128,
+            vault_owner: AccountId,
+        ) -> Result<(), VaultError> {
+            if self._get_debt_by_id(&vault_id) != 0 {
+                return Err(VaultError::HasDebt);
+            }
+            if self._get_collateral_by_id(&vault_id) != 0 {
+                return Err(VaultError::NotEmpty);
+            }
+            self._burn_from(vault_owner, Id::U128(vault_id))?;
+            // burned ids are never reused, stored attributes are cleared so they don't describe a burned vault
+            self._set_vault_attribute(vault_id, "collateral_token", Vec::new());
+            self._set_vault_attribute(vault_id, "created_at", Vec::new());
+            Ok(())
+        }
+
+        // transfers collateral in from and increases vault collateral
+        fn _deposit_collateral(
+            &mut self,
+            vault_id: u128,
+            from: AccountId,
+            amount: Balance,
+        ) -> Result<(), VaultError> {
+            self._transfer_collateral_in(from, amount)?;
+            self._credit_collateral(vault_id, amount);
+            Ok(())
+        }
+
+        // increases vault collateral by amount that is already transfered in
+        fn _credit_collateral(&mut self, vault_id: u128, amount: Balance) {
+            let collateral_after = self._get_collateral_by_id(&vault_id) + amount;
+            self.collateral_by_id.insert(&vault_id, &collateral_after);
+            self._emit_deposit_event(vault_id, collateral_after);
+        }
+
+        // decreases vault collateral and transfers it out to, collateralization is not checked
+        fn _withdraw_collateral(
+            &mut self,
+            vault_id: u128,
+            to: AccountId,
+            amount: Balance,
+        ) -> Result<(), VaultError> {
+            let vault_collateral = self._get_collateral_by_id(&vault_id);
+            if amount > vault_collateral {
+                return Err(VaultError::CollateralBelowMinimum);
+            }
+            let collateral_after = vault_collateral - amount;
+            self.collateral_by_id.insert(&vault_id, &collateral_after);
+            self._transfer_collateral_out(to, amount)?;
+            self._emit_withdraw_event(vault_id, collateral_after);
+            Ok(())
+        }
+
+        // updates vault debt, increases it and mints tokens to to, collateralization is not checked
+        fn _borrow_token(
+            &mut self,
+            vault_id: u128,
+            vault_owner: AccountId,
+            amount: Balance,
+            to: AccountId,
+        ) -> Result<(), VaultError> {
+            if FreezingViewRef::is_frozen(&self.emit.emited_token_address, vault_owner) {
+                return Err(VaultError::AccountFrozen);
+            }
+            let debt = self._update_vault_debt(vault_id)?;
+            self._check_debt_limits(debt + amount, amount)?;
+
+            // increase debt and borrow tokens
+            self.debt_by_id.insert(&vault_id, &(debt + amount));
+            PSP22RatedRef::add_account_debt(&self.emit.emited_token_address, vault_owner, amount)?;
+            self.total_debt += amount;
+            self._mint_emited_token(to, amount)?;
+
+            //event
+            self._emit_borrow_event(vault_id, amount);
+            Ok(())
+        }
+
+        // updates vault debt and pays back up to amount of it with payers tokens, returns repaid amount
+        fn _repay(
+            &mut self,
+            vault_id: u128,
+            vault_owner: AccountId,
+            payer: AccountId,
+            amount: Balance,
+        ) -> Result<Balance, VaultError> {
+            let debt = self._update_vault_debt(vault_id)?;
+            let repaid = if amount >= debt { debt } else { amount };
+            let debt_after = debt - repaid;
+            if debt_after != 0 && debt_after < self.min_vault_debt {
+                return Err(VaultError::DebtBelowMinimum);
+            }
+
+            // burn payers tokens, debt is removed from vault owner
+            self._burn_emited_token(payer, repaid)?;
+            self.debt_by_id.insert(&vault_id, &debt_after);
+            PSP22RatedRef::sub_account_debt(&self.emit.emited_token_address, vault_owner, repaid)?;
+            self.total_debt -= repaid;
+
+            //event
+            self._emit_pay_back_event(vault_id, payer, repaid);
+            Ok(repaid)
+        }
+
+        // updates vault debt and moves it from account_debt of from to account_debt of to
+        fn _move_vault_debt(
+            &mut self,
+            vault_id: u128,
+            from: AccountId,
+            to: AccountId,
+        ) -> Result<(), VaultError> {
+            let debt = self._update_vault_debt(vault_id)?;
+            if debt == 0 || from == to {
+                return Ok(());
+            }
+            if !self.accepts_debt_vaults.get(&to).unwrap_or(false) {
+                return Err(VaultError::DebtNotAccepted);
+            }
+            if FreezingViewRef::is_frozen(&self.emit.emited_token_address, to) {
+                return Err(VaultError::AccountFrozen);
+            }
+            PSP22RatedRef::sub_account_debt(&self.emit.emited_token_address, from, debt)?;
+            PSP22RatedRef::add_account_debt(&self.emit.emited_token_address, to, debt)?;
+            Ok(())
+        }
+
+        // collateral, debt with accrued interest and ratio bucket at current price, other attributes are stored
+        fn _vault_attribute(&self, vault_id: u128, key: &[u8]) -> Option<Vec<u8>> {
+            match key {
+                b"collateral" => Some(
+                    self._get_collateral_by_id(&vault_id)
+                        .to_string()
+                        .into_bytes(),
+                ),
+                b"debt" => Some(
+                    self._get_vault_debt_at(vault_id, self.env().block_timestamp())
+                        .to_string()
+                        .into_bytes(),
+                ),
+                b"ratio_bucket" => Some(
+                    self._ratio_bucket(self.get_health_factor_e6(vault_id))
+                        .as_bytes()
+                        .to_vec(),
+                ),
+                _ => self
+                    .metadata
+                    .attributes
+                    .get(&(Id::U128(vault_id), key.to_vec())),
+            }
+        }
+
+        fn _set_vault_attribute(&mut self, vault_id: u128, key: &str, value: Vec<u8>) {
+            PSP34MetadataInternal::_set_attribute(
+                self,
+                Id::U128(vault_id),
+                key.as_bytes().to_vec(),
+                value,
+            );
+        }
+
+        // groups vaults by health factor, "risky" vaults can be bought
+        fn _ratio_bucket(&self, health_factor_e6: u128) -> &'static str {
+            if health_factor_e6 == U128MAX {
+                "no_debt"
+            } else if health_factor_e6 < E6 {
+                "risky"
+            } else if health_factor_e6 < 3 * E6 / 2 {
+                "low"
+            } else if health_factor_e6 < 2 * E6 {
+                "medium"
+            } else {
+                "high"
+            }
+        }
+
+        // swaps amount_in of token_in already sent to swap adapter, returns increase of vaults token_out balance
+        fn _swap(
+            &mut self,
+            token_in: AccountId,
+            token_out: AccountId,
+            amount_in: Balance,
+            min_amount_out: Balance,
+        ) -> Result<Balance, VaultError> {
+            let vault_address = self.env().account_id();
+            let balance_before = PSP22Ref::balance_of(&token_out, vault_address);
+
+            // adapter sends output to the vault, which calls before_received of the vault
+            self.swapping.insert(&(), &true);
+            self.flush();
+            let result = SwapAdapterRef::swap_builder(
+                &self.swap_adapter_address,
+                token_in,
+                token_out,
+                amount_in,
+                min_amount_out,
+                vault_address,
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire();
+            self.load();
+            self.swapping.insert(&(), &false);
+            match result {
+                Ok(result) => result?,
+                Err(_) => return Err(VaultError::SwapFailed),
+            };
+
+            let received = PSP22Ref::balance_of(&token_out, vault_address) - balance_before;
+            if received < min_amount_out {
+                return Err(VaultError::SwapAdapterError(
+                    SwapAdapterError::InsufficientOutputAmount,
+                ));
+            }
+            Ok(received)
+        }
+
+        // updates vault debt and checks it is not above debt ceiling
+        fn _check_collateralization(&mut self, vault_id: u128) -> Result<(), VaultError> {
+            let debt = self._update_vault_debt(vault_id)?;
+            if self._health_factor_e6(self._get_debt_ceiling(vault_id), debt) < E6 {
+                return Err(VaultError::CollateralBelowMinimum);
+            }
+            Ok(())
+        }
+
+        // returns vault owner if caller is the owner or its operator
+        fn _check_vault_operator(&self, vault_id: u128) -> Result<AccountId, VaultError> {
+            let vault_owner: AccountId = match self._owner_of(&Id::U128(vault_id)) {
+                Some(v) => v,
+                None => return Err(VaultError::OwnerUnexists),
+            };
+            let caller = self.env().caller();
+            if caller != vault_owner && !self._is_operator(vault_owner, caller, Some(vault_id)) {
+                return Err(VaultError::VaultOwnership);
+            }
+            Ok(vault_owner)
+        }
+
+        // operator approved for all vaults is approved for each of them, PSP34 approval also counts
+        // approval for one vault is valid only until the vault is transfered, mapping entries can't be removed for all operators
+        fn _is_operator(
+            &self,
+            owner: AccountId,
+            operator: AccountId,
+            vault_id: Option<u128>,
+        ) -> bool {
+            if self
+                .operator_approvals
+                .get(&(owner, operator, None))
+                .unwrap_or(false)
+            {
+                return true;
+            }
+            match vault_id {
+                Some(id) => {
+                    (self
+                        .operator_approvals
+                        .get(&(owner, operator, Some(id)))
+                        .unwrap_or(false)
+                        && self.approval_epoch.get(&(owner, operator, id)).unwrap_or(0)
+                            == self.transfer_epoch_by_id.get(&id).unwrap_or(0))
+                        || self.allowance(owner, operator, Some(Id::U128(id)))
+                }
+                None => self.allowance(owner, operator, None),
+            }
+        }
+
+        fn _get_recipient(&self, owner: AccountId) -> AccountId {
+            self.recipient_of.get(&owner).unwrap_or(owner)
+        }
+
+        // appends vault to the list of owners vaults
+        fn _add_vault_to_owner(&mut self, owner: AccountId, vault_id: u128) {
+            let count = self.vault_count_of.get(&owner).unwrap_or(0);
+            self.vault_of_owner_by_index
+                .insert(&(owner, count), &vault_id);
+            self.index_of_vault.insert(&vault_id, &count);
+            self.vault_count_of.insert(&owner, &(count + 1));
+        }
+
+        // moves last vault of owner to the place of removed one
+        fn _remove_vault_from_owner(&mut self, owner: AccountId, vault_id: u128) {
+            let count = self.vault_count_of.get(&owner).unwrap_or(0);
+            if count == 0 {
+                return;
+            }
+            let last_index = count - 1;
+            let index = self.index_of_vault.get(&vault_id).unwrap_or(last_index);
+            if index != last_index {
+                let last_vault_id = self
+                    .vault_of_owner_by_index
+                    .get(&(owner, last_index))
+                    .unwrap_or_default();
+                self.vault_of_owner_by_index
+                    .insert(&(owner, index), &last_vault_id);
+                self.index_of_vault.insert(&last_vault_id, &index);
+            }
+            self.vault_count_of.insert(&owner, &last_index);
+        }
+
+        // collateral value / debt, U128MAX if there is no debt
+        fn _collateral_ratio_e6(&self, collateral_value_e6: u128, debt: Balance) -> u128 {
+            if debt == 0 {
+                return U128MAX;
+            }
+            collateral_value_e6 * E6 / debt
+        }
+
+        // debt ceiling / debt, vault can be bought when it is below E6
+        fn _health_factor_e6(&self, debt_ceiling: Balance, debt: Balance) -> u128 {
+            if debt == 0 {
+                return U128MAX;
+            }
+            debt_ceiling * E6 / debt
+        }
+
+        // collateral price at which debt ceiling falls to debt
+        fn _liquidation_price_e6(&self, collateral: Balance, debt: Balance) -> u128 {
+            if debt == 0 {
+                return 0;
+            }
+            if collateral == 0 {
+                return U128MAX;
+            }
+            debt * self.current_minimum_collateral_coefficient_e6 * COLLATERAL_DECIMALS
+                / (collateral * E6)
+        }
+
+        // debt that can be added before health factor falls below E6
+        fn _max_borrowable(&self, debt_ceiling: Balance, debt: Balance) -> Balance {
+            debt_ceiling.saturating_sub(debt)
+        }
+
+        // collateral that can be removed before health factor falls below E6
+        // rounds needed collateral up, so debt ceiling after withdraw is never below debt
+        fn _max_withdrawable(&self, collateral: Balance, debt: Balance) -> Balance {
+            if debt == 0 {
+                return collateral;
+            }
+            let collateral_price_e6 = OraclingRef::get_azero_usd_price_e6(&self.oracle_address);
+            if collateral_price_e6 == 0 {
+                return 0;
+            }
+            let needed_value_e6 =
+                (debt * self.current_minimum_collateral_coefficient_e6 + E6 - 1) / E6;
+            let needed_collateral = (needed_value_e6 * COLLATERAL_DECIMALS + collateral_price_e6
+                - 1)
+                / collateral_price_e6;
+            collateral.saturating_sub(needed_collateral)
+        }
+
         // return maximal debt for a vault
         fn _get_debt_ceiling(&self, vault_id: u128) -> Balance {
             ink_env::debug_println!("_get_debt_ceiling:");
@@ -573,14 +1746,17 @@ pub mod vault {
                 last_interest_coefficient_e12
             );
             // update
-            let mut updated_debt =
-                debt * current_interest_coefficient_e12 / last_interest_coefficient_e12;
-            if updated_debt != 0 {
-                updated_debt += 1; // round up
-            }
+            let updated_debt = _accrued_debt(
+                debt,
+                current_interest_coefficient_e12,
+                last_interest_coefficient_e12,
+            );
             ink_env::debug_println!("updated_debt: {}", updated_debt);
+            // total_debt follows accrued interest, so repaying a whole vault never subtracts more than was added
+            // and max_total_debt also limits debt grown by interest
             let vault_owner = self._owner_of(&Id::U128(vault_id)).unwrap_or_default(); //there will always be non default owner as owner must be caller. it is chacked before each _update_vault call
             if updated_debt > debt {
+                self.total_debt += updated_debt - debt;
                 self._add_profit_and_increase_shares_minting_allowance(
                     updated_debt - debt,
                     vault_owner,
@@ -591,6 +1767,7 @@ pub mod vault {
                     updated_debt - debt,
                 )?;
             } else if updated_debt < debt {
+                self.total_debt -= debt - updated_debt;
                 self._sub_profit(debt - updated_debt);
                 PSP22RatedRef::sub_account_debt(
                     &self.emit.emited_token_address,
@@ -609,33 +1786,52 @@ pub mod vault {
         // calculates, updates and returns current interest coefficient
         fn _update_current_interest_coefficient_e12(&mut self) -> u128 {
             let block_timestamp = self.env().block_timestamp();
-            let last_block_timestamp = self.last_interest_coefficient_timestamp;
-            if block_timestamp > last_block_timestamp {
+            if block_timestamp > self.last_interest_coefficient_timestamp {
+                self.current_interest_coefficient_e12 =
+                    self._get_interest_coefficient_at_e12(block_timestamp);
                 self.last_interest_coefficient_timestamp = block_timestamp;
-                let interest_rate: i128 = self.current_interest_rate_e12;
-                self.current_interest_coefficient_e12 = self.current_interest_coefficient_e12
-                    * (E12 as i128
-                        + (block_timestamp - last_block_timestamp) as i128 * interest_rate)
-                        as u128
-                    / E12;
             }
             self.current_interest_coefficient_e12
         }
 
         // calculates and retuns current interest coefficient
         fn _get_current_interest_coefficient_e12(&self) -> u128 {
-            let block_timestamp = self.env().block_timestamp();
-            let last_block_timestamp = self.last_interest_coefficient_timestamp;
-            let mut ret = self.current_interest_coefficient_e12;
-            if block_timestamp > last_block_timestamp {
-                let interest_rate = self.current_interest_rate_e12;
-                ret = ret
-                    * (E12 as i128
-                        + (block_timestamp - last_block_timestamp) as i128 * interest_rate)
-                        as u128
-                    / E12;
+            self._get_interest_coefficient_at_e12(self.env().block_timestamp())
+        }
+
+        // interest is compounded every timestamp unit: coefficient * (1 + rate)^dt
+        fn _get_interest_coefficient_at_e12(&self, timestamp: Timestamp) -> u128 {
+            let last_timestamp = self.last_interest_coefficient_timestamp;
+            if timestamp <= last_timestamp {
+                return self.current_interest_coefficient_e12;
             }
-            ret
+            // rate below -100% would make the base negative, debt can at most vanish
+            let base_e12: u128 = (E12 as i128 + self._get_interest_rate_e12()).max(0) as u128;
+            // coefficient can't reach 0, accrued debt is divided by it
+            (self
+                .current_interest_coefficient_e12
+                .saturating_mul(rpow_e12(base_e12, timestamp - last_timestamp))
+                / E12)
+                .max(1)
+        }
+
+        // controller driven rate increased by premium of this vault type
+        fn _get_interest_rate_e12(&self) -> i128 {
+            self.current_interest_rate_e12 + self.interest_rate_premium_e12
+        }
+
+        // calculates debt of a vault at timestamp without updating storage
+        fn _get_vault_debt_at(&self, vault_id: u128, timestamp: Timestamp) -> Balance {
+            let last_interest_coefficient_e12 =
+                self._get_last_interest_coefficient_by_id_e12(&vault_id);
+            if last_interest_coefficient_e12 == 0 {
+                return 0;
+            }
+            _accrued_debt(
+                self._get_debt_by_id(&vault_id),
+                self._get_interest_coefficient_at_e12(timestamp),
+                last_interest_coefficient_e12,
+            )
         }
 
         // returns vaule from mapping
@@ -648,6 +1844,28 @@ pub mod vault {
             self.collateral_by_id.get(&vault_id).unwrap_or(0)
         }
 
+        // checks dust floor of a vault and debt ceilings of collateral and system
+        fn _check_debt_limits(
+            &self,
+            debt_after: Balance,
+            borrowed: Balance,
+        ) -> Result<(), VaultError> {
+            if debt_after != 0 && debt_after < self.min_vault_debt {
+                return Err(VaultError::DebtBelowMinimum);
+            }
+            if self.total_debt + borrowed > self.max_total_debt {
+                return Err(VaultError::TotalDebtAboveMaximum);
+            }
+            if self.max_emited_supply != U128MAX {
+                let emited_supply: Balance =
+                    PSP22Ref::total_supply(&self.emit.emited_token_address);
+                if emited_supply + borrowed > self.max_emited_supply {
+                    return Err(VaultError::EmitedSupplyAboveMaximum);
+                }
+            }
+            Ok(())
+        }
+
         // returns value from mapping
         fn _get_last_interest_coefficient_by_id_e12(&self, vault_id: &u128) -> Balance {
             self.last_interest_coefficient_by_id_e12
@@ -656,6 +1874,17 @@ pub mod vault {
         }
     }
 
+    // debt * current / last rounded up, so rounding always favours the system
+    fn _accrued_debt(
+        debt: Balance,
+        current_interest_coefficient_e12: u128,
+        last_interest_coefficient_e12: u128,
+    ) -> Balance {
+        debt.saturating_mul(current_interest_coefficient_e12)
+            .saturating_add(last_interest_coefficient_e12 - 1)
+            / last_interest_coefficient_e12
+    }
+
     #[ink(event)]
     pub struct OwnershipTransferred {
         #[ink(topic)]
@@ -717,6 +1946,42 @@ pub mod vault {
         approved: bool,
     }
     impl PSP34Internal for VaultContract {
+        // keeps lists of owners vaults up to date on mint, burn and transfer
+        // on transfer debt of the vault is moved with it and operators approved for the vault lose approval
+        #[modifiers(when_migrated)]
+        fn _before_token_transfer(
+            &mut self,
+            from: Option<&AccountId>,
+            to: Option<&AccountId>,
+            id: &Id,
+        ) -> Result<(), PSP34Error> {
+            if let Id::U128(vault_id) = id {
+                if let (Some(from), Some(to)) = (from, to) {
+                    self._move_vault_debt(*vault_id, *from, *to)
+                        .map_err(|error| match error {
+                            VaultError::PSP34Error(error) => error,
+                            VaultError::DebtNotAccepted => {
+                                PSP34Error::Custom(String::from("DebtNotAccepted"))
+                            }
+                            VaultError::AccountFrozen => {
+                                PSP34Error::Custom(String::from("AccountFrozen"))
+                            }
+                            _ => PSP34Error::Custom(String::from("VaultDebtNotMoved")),
+                        })?;
+                    let epoch = self.transfer_epoch_by_id.get(vault_id).unwrap_or(0);
+                    self.transfer_epoch_by_id
+                        .insert(vault_id, &epoch.wrapping_add(1));
+                }
+                if let Some(from) = from {
+                    self._remove_vault_from_owner(*from, *vault_id);
+                }
+                if let Some(to) = to {
+                    self._add_vault_to_owner(*to, *vault_id);
+                }
+            }
+            Ok(())
+        }
+
         /// Emits transfer event. This method must be implemented in derived implementation
         fn _emit_transfer_event(&self, _from: Option<AccountId>, _to: Option<AccountId>, _id: Id) {
             self.env().emit_event(Transfer {
@@ -743,30 +2008,361 @@ pub mod vault {
         }
     }
 
+    #[ink(event)]
+    pub struct Upgraded {
+        old_code_hash: Option<[u8; 32]>,
+        new_code_hash: [u8; 32],
+    }
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    impl UpgradingInternal for VaultContract {
+        fn _code_storage_version(&self) -> u32 {
+            STORAGE_VERSION
+        }
+
+        fn _migrate(&mut self, _from_version: u32) -> Result<(), UpgradingError> {
+            // fields of versions 2 to 4 are mappings, entries missing in older storage read as defaults:
+            // 2 - swapping reads as false
+            // 3 - transfer epochs read as 0, so approvals given before the upgrade stay valid until the next transfer of the vault
+            // 4 - flash_fee_collateral reads as 0, fees collected before were booked as profit
+            Ok(())
+        }
+
+        fn _emit_upgraded_event(&self, _old_code_hash: Option<[u8; 32]>, _new_code_hash: [u8; 32]) {
+            self.env().emit_event(Upgraded {
+                old_code_hash: _old_code_hash,
+                new_code_hash: _new_code_hash,
+            });
+        }
+
+        fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32) {
+            self.env().emit_event(Migrated {
+                from_version: _from_version,
+                to_version: _to_version,
+            });
+        }
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
         use brush::test_utils::{accounts, change_caller};
-        use brush::traits::AccountId;
         use ink_lang as ink;
-        type Event = <VaultContract as ::ink_lang::reflect::ContractEventBase>::Type;
-        use ink_env::test::DefaultAccounts;
-        use ink_env::DefaultEnvironment;
+
+        fn setup() -> VaultContract {
+            let accounts = accounts();
+            change_caller(accounts.alice);
+            VaultContract::new(
+                accounts.bob,
+                accounts.charlie,
+                accounts.django,
+                accounts.eve,
+                accounts.frank,
+                2 * E6,
+                10_000,
+                0,
+                accounts.alice,
+            )
+        }
 
         #[ink::test]
         fn constructor_works() {
-            // Constructor works.
             let accounts = accounts();
-            let mut vault = VaultContract::new(accounts.bob, accounts.charlie, accounts.alice);
-            // Transfer event triggered during initial construction.
-            let emitted_events = ink_env::test::recorded_events().collect::<Vec<_>>();
-            assert_eq!(emitted_events.len(), 1);
+            let vault = setup();
             assert_eq!(vault.owner(), accounts.alice);
-            // Get the token total supply.
+            assert_eq!(vault.get_oracle_address(), accounts.bob);
+            assert_eq!(vault.get_shares_token_address(), accounts.charlie);
+            assert_eq!(vault.get_collateral_token_address(), accounts.eve);
+            assert_eq!(vault.get_emited_token_address(), accounts.frank);
+            assert_eq!(vault.get_max_total_debt(), U128MAX);
+            assert_eq!(vault.get_max_emited_supply(), U128MAX);
+        }
 
-            assert_eq!(vault.get_collateral_token_address(), accounts.bob);
-            assert_eq!(vault.get_emited_token_address(), accounts.charlie);
-            assert_eq!(vault.get_vault_controller_address(), accounts.alice);
+        #[ink::test]
+        fn debt_limits_check_dust_floor_and_total_debt() {
+            let accounts = accounts();
+            let mut vault = setup();
+            assert_eq!(vault.set_min_vault_debt(100), Ok(()));
+            assert_eq!(vault.set_max_total_debt(1_000), Ok(()));
+            // vault can always be repaid to zero
+            assert_eq!(vault._check_debt_limits(0, 0), Ok(()));
+            assert_eq!(
+                vault._check_debt_limits(99, 99),
+                Err(VaultError::DebtBelowMinimum)
+            );
+            assert_eq!(vault._check_debt_limits(100, 100), Ok(()));
+            vault.total_debt = 900;
+            assert_eq!(vault._check_debt_limits(200, 100), Ok(()));
+            assert_eq!(
+                vault._check_debt_limits(201, 101),
+                Err(VaultError::TotalDebtAboveMaximum)
+            );
+            // only owner sets the limits
+            change_caller(accounts.bob);
+            assert_eq!(
+                vault.set_min_vault_debt(0),
+                Err(VaultError::OwnableError(OwnableError::CallerIsNotOwner))
+            );
+            assert_eq!(
+                vault.set_max_total_debt(U128MAX),
+                Err(VaultError::OwnableError(OwnableError::CallerIsNotOwner))
+            );
+            assert_eq!(vault.get_min_vault_debt(), 100);
+            assert_eq!(vault.get_max_total_debt(), 1_000);
+        }
+
+        // moves vault NFT like a PSP34 transfer, without the receiver check that calls the recipient
+        fn transfer_vault(
+            vault: &mut VaultContract,
+            from: AccountId,
+            to: AccountId,
+            vault_id: u128,
+        ) {
+            let id = Id::U128(vault_id);
+            assert_eq!(
+                vault._before_token_transfer(Some(&from), Some(&to), &id),
+                Ok(())
+            );
+            assert_eq!(vault._remove_token(&from, &id), Ok(()));
+            assert_eq!(vault._add_token(&to, &id), Ok(()));
+        }
+
+        #[ink::test]
+        fn operator_approvals_are_per_vault_or_for_all() {
+            let accounts = accounts();
+            let mut vault = setup();
+            let vault_id = vault._create_vault(accounts.bob).unwrap();
+            change_caller(accounts.bob);
+            assert!(!vault.is_operator(accounts.bob, accounts.charlie, Some(vault_id)));
+            assert_eq!(
+                vault.approve_operator(accounts.charlie, Some(vault_id), true),
+                Ok(())
+            );
+            assert!(vault.is_operator(accounts.bob, accounts.charlie, Some(vault_id)));
+            assert!(!vault.is_operator(accounts.bob, accounts.charlie, None));
+            assert_eq!(
+                vault.approve_operator(accounts.bob, None, true),
+                Err(VaultError::PSP34Error(PSP34Error::SelfApprove))
+            );
+
+            change_caller(accounts.charlie);
+            assert_eq!(vault._check_vault_operator(vault_id), Ok(accounts.bob));
+            // operator can not approve others for the vault
+            assert_eq!(
+                vault.approve_operator(accounts.django, Some(vault_id), true),
+                Err(VaultError::VaultOwnership)
+            );
+            change_caller(accounts.django);
+            assert_eq!(
+                vault._check_vault_operator(vault_id),
+                Err(VaultError::VaultOwnership)
+            );
+
+            change_caller(accounts.bob);
+            assert_eq!(
+                vault.approve_operator(accounts.charlie, Some(vault_id), false),
+                Ok(())
+            );
+            assert!(!vault.is_operator(accounts.bob, accounts.charlie, Some(vault_id)));
+            assert_eq!(vault.approve_operator(accounts.django, None, true), Ok(()));
+            assert!(vault.is_operator(accounts.bob, accounts.django, None));
+            assert!(vault.is_operator(accounts.bob, accounts.django, Some(vault_id)));
+            change_caller(accounts.django);
+            assert_eq!(vault._check_vault_operator(vault_id), Ok(accounts.bob));
+        }
+
+        #[ink::test]
+        fn vault_approval_ends_on_transfer() {
+            let accounts = accounts();
+            let mut vault = setup();
+            let vault_id = vault._create_vault(accounts.bob).unwrap();
+            change_caller(accounts.bob);
+            assert_eq!(
+                vault.approve_operator(accounts.charlie, Some(vault_id), true),
+                Ok(())
+            );
+            transfer_vault(&mut vault, accounts.bob, accounts.eve, vault_id);
+            assert!(!vault.is_operator(accounts.bob, accounts.charlie, Some(vault_id)));
+            // approval given before the vault left does not come back with it
+            transfer_vault(&mut vault, accounts.eve, accounts.bob, vault_id);
+            assert!(!vault.is_operator(accounts.bob, accounts.charlie, Some(vault_id)));
+            change_caller(accounts.charlie);
+            assert_eq!(
+                vault._check_vault_operator(vault_id),
+                Err(VaultError::VaultOwnership)
+            );
+            // new approval is valid again
+            change_caller(accounts.bob);
+            assert_eq!(
+                vault.approve_operator(accounts.charlie, Some(vault_id), true),
+                Ok(())
+            );
+            assert!(vault.is_operator(accounts.bob, accounts.charlie, Some(vault_id)));
+        }
+
+        #[ink::test]
+        fn transfer_and_call_credits_chosen_vault() {
+            let accounts = accounts();
+            let mut vault = setup();
+            let vault_id = vault._create_vault(accounts.bob).unwrap();
+            // collateral token calls the vault when collateral is transfered to it
+            change_caller(accounts.eve);
+            assert_eq!(
+                vault.before_received(
+                    accounts.bob,
+                    accounts.bob,
+                    1_000,
+                    DepositTarget::Vault(vault_id).encode()
+                ),
+                Ok(())
+            );
+            assert_eq!(vault.get_vault_details(vault_id), (1_000, 0));
+            assert_eq!(
+                vault.before_received(
+                    accounts.charlie,
+                    accounts.charlie,
+                    500,
+                    DepositTarget::NewVault.encode()
+                ),
+                Ok(())
+            );
+            let new_vault_id = vault_id + 1;
+            assert_eq!(
+                vault.owner_of(Id::U128(new_vault_id)),
+                Some(accounts.charlie)
+            );
+            assert_eq!(vault.get_vault_details(new_vault_id), (500, 0));
+            assert_eq!(vault.collateral_amount(), 1_500);
+        }
+
+        #[ink::test]
+        fn transfer_and_call_rejects_undirected_and_other_tokens() {
+            let accounts = accounts();
+            let mut vault = setup();
+            let vault_id = vault._create_vault(accounts.bob).unwrap();
+            change_caller(accounts.eve);
+            assert_eq!(
+                vault.before_received(accounts.bob, accounts.bob, 1_000, Vec::new()),
+                Err(PSP22ReceiverError::TransferRejected(
+                    "UndirectedDeposit".to_string()
+                ))
+            );
+            assert_eq!(
+                vault.before_received(
+                    accounts.bob,
+                    accounts.bob,
+                    1_000,
+                    DepositTarget::Vault(vault_id + 1).encode()
+                ),
+                Err(PSP22ReceiverError::TransferRejected(
+                    "OwnerUnexists".to_string()
+                ))
+            );
+            change_caller(accounts.django);
+            assert_eq!(
+                vault.before_received(
+                    accounts.bob,
+                    accounts.bob,
+                    1_000,
+                    DepositTarget::Vault(vault_id).encode()
+                ),
+                Err(PSP22ReceiverError::TransferRejected(
+                    "UnacceptedPsp22".to_string()
+                ))
+            );
+            assert_eq!(vault.get_vault_details(vault_id), (0, 0));
+            assert_eq!(vault.collateral_amount(), 0);
+        }
+
+        #[ink::test]
+        fn vaults_of_follows_mint_transfer_and_burn() {
+            let accounts = accounts();
+            let mut vault = setup();
+            for _ in 0..3 {
+                vault._create_vault(accounts.bob).unwrap();
+            }
+            assert_eq!(vault.vaults_of(accounts.bob, 0, 10), vec![0, 1, 2]);
+            assert_eq!(vault.vaults_of(accounts.bob, 1, 1), vec![1]);
+            assert_eq!(vault.vaults_of(accounts.bob, 3, 10), Vec::<u128>::new());
+
+            // last vault of the sender takes the place of transfered one
+            transfer_vault(&mut vault, accounts.bob, accounts.charlie, 0);
+            assert_eq!(vault.vaults_of(accounts.bob, 0, 10), vec![2, 1]);
+            assert_eq!(vault.vaults_of(accounts.charlie, 0, 10), vec![0]);
+
+            assert_eq!(vault._destroy_vault(1, accounts.bob), Ok(()));
+            assert_eq!(vault.vaults_of(accounts.bob, 0, 10), vec![2]);
+            transfer_vault(&mut vault, accounts.bob, accounts.charlie, 2);
+            assert_eq!(vault.vaults_of(accounts.bob, 0, 10), Vec::<u128>::new());
+            assert_eq!(vault.vaults_of(accounts.charlie, 0, 10), vec![0, 2]);
+        }
+
+        #[ink::test]
+        fn health_factor_max_borrowable_and_liquidation_price() {
+            let vault = setup();
+            assert_eq!(vault._health_factor_e6(1_000, 0), U128MAX);
+            assert_eq!(vault._health_factor_e6(1_500, 1_000), 1_500_000);
+            assert_eq!(vault._health_factor_e6(999, 1_000), 999_000);
+            assert_eq!(vault._max_borrowable(1_500, 1_000), 500);
+            assert_eq!(vault._max_borrowable(999, 1_000), 0);
+            assert_eq!(vault._liquidation_price_e6(COLLATERAL_DECIMALS, 0), 0);
+            assert_eq!(vault._liquidation_price_e6(0, 1), U128MAX);
+            // at minimum collateral coefficient 2 one collateral token backs 0.6 stable coin down to price 1.2
+            assert_eq!(
+                vault._liquidation_price_e6(COLLATERAL_DECIMALS, 600_000),
+                1_200_000
+            );
+            assert_eq!(
+                vault._liquidation_price_e6(2 * COLLATERAL_DECIMALS, 600_000),
+                600_000
+            );
+            assert_eq!(vault._collateral_ratio_e6(1_200_000, 600_000), 2 * E6);
+            assert_eq!(vault._collateral_ratio_e6(1_200_000, 0), U128MAX);
+        }
+
+        #[ink::test]
+        fn rpow_compounds_interest() {
+            assert_eq!(rpow_e12(2 * E12, 0), E12);
+            assert_eq!(rpow_e12(2 * E12, 10), 1024 * E12);
+            assert_eq!(rpow_e12(E12 / 2, 3), E12 / 8);
+            assert_eq!(rpow_e12(0, 5), 0);
+            // compounding gives more than linear growth
+            assert_eq!(rpow_e12(E12 + E12 / 10, 2), 121 * E12 / 100);
+        }
+
+        #[ink::test]
+        fn accrued_debt_rounds_up() {
+            assert_eq!(_accrued_debt(0, 3 * E12, E12), 0);
+            assert_eq!(_accrued_debt(100, E12, E12), 100);
+            assert_eq!(_accrued_debt(100, E12 + 1, E12), 101);
+        }
+
+        #[ink::test]
+        fn vault_opens_after_accrual_at_minus_100_percent() {
+            let accounts = accounts();
+            let mut vault = setup();
+            vault._create_vault(accounts.bob).unwrap();
+            vault.debt_by_id.insert(&0, &1_000);
+            assert!(vault.set_interest_rate_premium_e12(-(E12 as i128)).is_ok());
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>();
+            assert_eq!(vault._update_current_interest_coefficient_e12(), 1);
+            // debt vanishes down to rounding
+            assert_eq!(
+                vault._get_vault_debt_at(0, vault.last_interest_coefficient_timestamp),
+                1
+            );
+
+            vault._create_vault(accounts.bob).unwrap();
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>();
+            assert_eq!(vault._update_vault_debt(1), Ok(0));
+            assert_eq!(
+                vault._get_vault_debt_at(1, vault.last_interest_coefficient_timestamp),
+                0
+            );
         }
     }
 }
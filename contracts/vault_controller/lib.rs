@@ -4,19 +4,27 @@
 #[brush::contract]
 pub mod lending {
     use brush::contracts::ownable::*;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
     use ink_storage::traits::SpreadAllocate;
+    use stable_coin_project::impls::upgrading::*;
     use stable_coin_project::impls::vault_controlling::*;
 
+    const STORAGE_VERSION: u32 = 1;
+
     #[ink(storage)]
-    #[derive(Default, SpreadAllocate, OwnableStorage, VControllingStorage)]
+    #[derive(Default, SpreadAllocate, OwnableStorage, VControllingStorage, UpgradingStorage)]
     pub struct VControllerContract {
         #[OwnableStorageField]
         owner: OwnableData,
         #[VControllingStorageField]
         control: VControllingData,
+        #[UpgradingStorageField]
+        upgrading: UpgradingData,
     }
 
     impl Ownable for VControllerContract {}
+    impl Upgrading for VControllerContract {}
 
     impl VControlling for VControllerContract {}
 
@@ -36,7 +44,39 @@ pub mod lending {
                 instance.control.measurer_address = measurer_address;
                 instance.control.vault_address = vault_address;
                 instance._init_with_owner(owner);
+                instance.upgrading.storage_version = STORAGE_VERSION;
             })
         }
     }
+
+    #[ink(event)]
+    pub struct Upgraded {
+        old_code_hash: Option<[u8; 32]>,
+        new_code_hash: [u8; 32],
+    }
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    impl UpgradingInternal for VControllerContract {
+        fn _code_storage_version(&self) -> u32 {
+            STORAGE_VERSION
+        }
+
+        fn _emit_upgraded_event(&self, _old_code_hash: Option<[u8; 32]>, _new_code_hash: [u8; 32]) {
+            self.env().emit_event(Upgraded {
+                old_code_hash: _old_code_hash,
+                new_code_hash: _new_code_hash,
+            });
+        }
+
+        fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32) {
+            self.env().emit_event(Migrated {
+                from_version: _from_version,
+                to_version: _to_version,
+            });
+        }
+    }
 }
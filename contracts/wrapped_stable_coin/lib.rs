@@ -0,0 +1,502 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// Wrapped stable coin (waUSD). Holds rated stable coin and issues shares with fixed balances,
+/// so AMMs and lending markets can use it. Value of a share follows current_denominator_e12 of the stable coin.
+/// Wrapper has to be tax free on stable coin.
+#[brush::contract]
+pub mod wrapped_stable_coin {
+    use brush::{
+        contracts::ownable::*, contracts::psp22::extensions::metadata::*, contracts::psp22::*,
+        modifiers,
+    };
+    use ink_env::CallFlags;
+    use ink_lang::codegen::EmitEvent;
+    use ink_lang::codegen::Env;
+    use ink_prelude::{string::String, vec::Vec};
+    use ink_storage::traits::SpreadAllocate;
+    use stable_coin_project::impls::upgrading::*;
+    use stable_coin_project::traits::psp22_rated::*;
+    use stable_coin_project::traits::wrapping::*;
+
+    const STORAGE_VERSION: u32 = 1;
+    // rated transfer can credit the wrapper up to 2 units less than sent (scaled amount and balance are rounded down)
+    const TRANSFER_ROUNDING: Balance = 2;
+
+    #[ink(storage)]
+    #[derive(
+        Default,
+        SpreadAllocate,
+        OwnableStorage,
+        PSP22Storage,
+        PSP22MetadataStorage,
+        UpgradingStorage,
+    )]
+    pub struct WrappedStableCoinContract {
+        #[OwnableStorageField]
+        ownable: OwnableData,
+        #[PSP22StorageField]
+        psp22: PSP22Data,
+        #[PSP22MetadataStorageField]
+        metadata: PSP22MetadataData,
+        #[UpgradingStorageField]
+        upgrading: UpgradingData,
+
+        // immutables
+        pub stable_coin_address: AccountId,
+    }
+
+    impl WrappedStableCoinContract {
+        #[ink(constructor)]
+        pub fn new(
+            name: Option<String>,
+            symbol: Option<String>,
+            decimal: u8,
+            stable_coin_address: AccountId,
+            owner: AccountId,
+        ) -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                instance.metadata.name = name;
+                instance.metadata.symbol = symbol;
+                instance.metadata.decimals = decimal;
+                instance._init_with_owner(owner);
+                instance.upgrading.storage_version = STORAGE_VERSION;
+                instance.stable_coin_address = stable_coin_address;
+            })
+        }
+    }
+
+    impl Ownable for WrappedStableCoinContract {}
+    impl Upgrading for WrappedStableCoinContract {}
+
+    impl PSP22 for WrappedStableCoinContract {}
+    impl PSP22Metadata for WrappedStableCoinContract {}
+
+    impl PSP22Internal for WrappedStableCoinContract {
+        fn _emit_transfer_event(
+            &self,
+            _from: Option<AccountId>,
+            _to: Option<AccountId>,
+            _amount: Balance,
+        ) {
+            self.env().emit_event(Transfer {
+                from: _from,
+                to: _to,
+                value: _amount,
+            });
+        }
+
+        // transfers of shares run pending migrations
+        #[modifiers(when_migrated)]
+        fn _before_token_transfer(
+            &mut self,
+            _from: Option<&AccountId>,
+            _to: Option<&AccountId>,
+            _amount: &Balance,
+        ) -> Result<(), PSP22Error> {
+            Ok(())
+        }
+
+        fn _emit_approval_event(&self, _owner: AccountId, _spender: AccountId, _amount: Balance) {
+            self.env().emit_event(Approval {
+                owner: _owner,
+                spender: _spender,
+                value: _amount,
+            })
+        }
+    }
+
+    impl Wrapping for WrappedStableCoinContract {
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn deposit(
+            &mut self,
+            assets: Balance,
+            receiver: AccountId,
+        ) -> Result<Balance, WrappingError> {
+            if assets == 0 {
+                return Err(WrappingError::ZeroAmount);
+            }
+            self._check_tax_free()?;
+            let total_assets_before = self.total_assets();
+            let total_supply = self.psp22.supply;
+            // rated balance of the wrapper can grow by less than assets because of rounding
+            let received = self._transfer_assets_in(assets)?;
+            let shares = _mul_div(received, total_supply + 1, total_assets_before + 1, false)?;
+            if shares == 0 {
+                return Err(WrappingError::ZeroAmount);
+            }
+            self._mint(receiver, shares)?;
+            self._emit_deposit_event(receiver, received, shares);
+            Ok(shares)
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn mint(&mut self, shares: Balance, receiver: AccountId) -> Result<Balance, WrappingError> {
+            if shares == 0 {
+                return Err(WrappingError::ZeroAmount);
+            }
+            self._check_tax_free()?;
+            let needed = self._convert_to_assets(shares, true)?;
+            // rounding slack is taken on top, so received assets cover minted shares
+            let assets = needed
+                .checked_add(TRANSFER_ROUNDING)
+                .ok_or(WrappingError::Overflow)?;
+            let received = self._transfer_assets_in(assets)?;
+            if received < needed {
+                return Err(WrappingError::InsufficientAssetsReceived);
+            }
+            self._mint(receiver, shares)?;
+            self._emit_deposit_event(receiver, assets, shares);
+            Ok(assets)
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn withdraw(
+            &mut self,
+            assets: Balance,
+            receiver: AccountId,
+            owner: AccountId,
+        ) -> Result<Balance, WrappingError> {
+            if assets == 0 {
+                return Err(WrappingError::ZeroAmount);
+            }
+            if assets > self._convert_to_assets(self._balance_of(&owner), false)? {
+                return Err(WrappingError::AmountAboveMaximum);
+            }
+            let shares = self._convert_to_shares(assets, true)?;
+            self._burn_shares(owner, shares)?;
+            self._transfer_assets_out(receiver, assets)?;
+            self._emit_withdraw_event(receiver, owner, assets, shares);
+            Ok(shares)
+        }
+
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn redeem(
+            &mut self,
+            shares: Balance,
+            receiver: AccountId,
+            owner: AccountId,
+        ) -> Result<Balance, WrappingError> {
+            if shares > self.max_redeem(owner) {
+                return Err(WrappingError::AmountAboveMaximum);
+            }
+            let assets = self._convert_to_assets(shares, false)?;
+            if assets == 0 {
+                return Err(WrappingError::ZeroAmount);
+            }
+            self._burn_shares(owner, shares)?;
+            self._transfer_assets_out(receiver, assets)?;
+            self._emit_withdraw_event(receiver, owner, assets, shares);
+            Ok(assets)
+        }
+    }
+
+    // views that would overflow return the value least favourable to the caller, 0 when rounding down, Balance::MAX when rounding up
+    impl WrappingView for WrappedStableCoinContract {
+        #[ink(message)]
+        fn asset(&self) -> AccountId {
+            self.stable_coin_address
+        }
+
+        #[ink(message)]
+        fn total_assets(&self) -> Balance {
+            PSP22Ref::balance_of(&self.stable_coin_address, self.env().account_id())
+        }
+
+        #[ink(message)]
+        fn convert_to_shares(&self, assets: Balance) -> Balance {
+            self._convert_to_shares(assets, false).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        fn convert_to_assets(&self, shares: Balance) -> Balance {
+            self._convert_to_assets(shares, false).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        fn max_withdraw(&self, owner: AccountId) -> Balance {
+            self.convert_to_assets(self._balance_of(&owner))
+        }
+
+        #[ink(message)]
+        fn max_redeem(&self, owner: AccountId) -> Balance {
+            self._balance_of(&owner)
+        }
+
+        #[ink(message)]
+        fn preview_deposit(&self, assets: Balance) -> Balance {
+            self.convert_to_shares(assets)
+        }
+
+        // includes TRANSFER_ROUNDING taken by mint
+        #[ink(message)]
+        fn preview_mint(&self, shares: Balance) -> Balance {
+            self._convert_to_assets(shares, true)
+                .unwrap_or(Balance::MAX)
+                .saturating_add(TRANSFER_ROUNDING)
+        }
+
+        #[ink(message)]
+        fn preview_withdraw(&self, assets: Balance) -> Balance {
+            self._convert_to_shares(assets, true)
+                .unwrap_or(Balance::MAX)
+        }
+
+        #[ink(message)]
+        fn preview_redeem(&self, shares: Balance) -> Balance {
+            self.convert_to_assets(shares)
+        }
+    }
+
+    impl PSP22Receiver for WrappedStableCoinContract {
+        #[ink(message)]
+        #[modifiers(when_migrated)]
+        fn before_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            _value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22ReceiverError> {
+            Ok(())
+        }
+    }
+
+    impl WrappedStableCoinContract {
+        // +1 on both sides keeps the rate defined for empty wrapper and makes share price manipulation unprofitable
+        fn _convert_to_shares(
+            &self,
+            assets: Balance,
+            round_up: bool,
+        ) -> Result<Balance, WrappingError> {
+            _mul_div(
+                assets,
+                self.psp22.supply + 1,
+                self.total_assets() + 1,
+                round_up,
+            )
+        }
+
+        fn _convert_to_assets(
+            &self,
+            shares: Balance,
+            round_up: bool,
+        ) -> Result<Balance, WrappingError> {
+            _mul_div(
+                shares,
+                self.total_assets() + 1,
+                self.psp22.supply + 1,
+                round_up,
+            )
+        }
+
+        // taxed deposits would be worth less than the shares minted for them
+        fn _check_tax_free(&self) -> Result<(), WrappingError> {
+            if !PSP22RatedRef::is_tax_free(&self.stable_coin_address, self.env().account_id()) {
+                return Err(WrappingError::NotTaxFree);
+            }
+            Ok(())
+        }
+
+        // returns by how much the rated balance of the wrapper grew
+        fn _transfer_assets_in(&mut self, assets: Balance) -> Result<Balance, WrappingError> {
+            let total_assets_before = self.total_assets();
+            PSP22Ref::transfer_from_builder(
+                &self.stable_coin_address,
+                self.env().caller(),
+                self.env().account_id(),
+                assets,
+                Vec::<u8>::new(),
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()?;
+            Ok(self.total_assets() - total_assets_before)
+        }
+
+        fn _transfer_assets_out(
+            &mut self,
+            receiver: AccountId,
+            assets: Balance,
+        ) -> Result<(), WrappingError> {
+            PSP22Ref::transfer_builder(
+                &self.stable_coin_address,
+                receiver,
+                assets,
+                Vec::<u8>::new(),
+            )
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()?;
+            Ok(())
+        }
+
+        // caller other than owner spends allowance of owner
+        fn _burn_shares(&mut self, owner: AccountId, shares: Balance) -> Result<(), WrappingError> {
+            let caller = self.env().caller();
+            if caller != owner {
+                let allowance = self.allowance(owner, caller);
+                if allowance < shares {
+                    return Err(WrappingError::PSP22Error(PSP22Error::InsufficientAllowance));
+                }
+                self._approve_from_to(owner, caller, allowance - shares)?;
+            }
+            self._burn_from(owner, shares)?;
+            Ok(())
+        }
+
+        fn _emit_deposit_event(&self, owner: AccountId, assets: Balance, shares: Balance) {
+            self.env().emit_event(Deposit {
+                sender: self.env().caller(),
+                owner,
+                assets,
+                shares,
+            });
+        }
+
+        fn _emit_withdraw_event(
+            &self,
+            receiver: AccountId,
+            owner: AccountId,
+            assets: Balance,
+            shares: Balance,
+        ) {
+            self.env().emit_event(Withdraw {
+                sender: self.env().caller(),
+                receiver,
+                owner,
+                assets,
+                shares,
+            });
+        }
+    }
+
+    // amount * numerator / denominator rounded down or up
+    fn _mul_div(
+        amount: Balance,
+        numerator: Balance,
+        denominator: Balance,
+        round_up: bool,
+    ) -> Result<Balance, WrappingError> {
+        let product = amount
+            .checked_mul(numerator)
+            .ok_or(WrappingError::Overflow)?;
+        if round_up && product % denominator != 0 {
+            Ok(product / denominator + 1)
+        } else {
+            Ok(product / denominator)
+        }
+    }
+
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Deposit {
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        owner: AccountId,
+        assets: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdraw {
+        #[ink(topic)]
+        sender: AccountId,
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        owner: AccountId,
+        assets: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Upgraded {
+        old_code_hash: Option<[u8; 32]>,
+        new_code_hash: [u8; 32],
+    }
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    impl UpgradingInternal for WrappedStableCoinContract {
+        fn _code_storage_version(&self) -> u32 {
+            STORAGE_VERSION
+        }
+
+        fn _emit_upgraded_event(&self, _old_code_hash: Option<[u8; 32]>, _new_code_hash: [u8; 32]) {
+            self.env().emit_event(Upgraded {
+                old_code_hash: _old_code_hash,
+                new_code_hash: _new_code_hash,
+            });
+        }
+
+        fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32) {
+            self.env().emit_event(Migrated {
+                from_version: _from_version,
+                to_version: _to_version,
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_lang as ink;
+
+        #[ink::test]
+        fn empty_wrapper_converts_one_to_one() {
+            // supply + 1 and total_assets + 1 of empty wrapper
+            assert_eq!(_mul_div(1_000_000, 1, 1, false), Ok(1_000_000));
+            assert_eq!(_mul_div(1_000_000, 1, 1, true), Ok(1_000_000));
+        }
+
+        #[ink::test]
+        fn rounding_favours_wrapper() {
+            // 1000 shares backed by 1500 assets
+            let (supply, total_assets) = (1_000, 1_500);
+            let shares_for_deposit = _mul_div(10, supply + 1, total_assets + 1, false).unwrap();
+            let shares_for_withdraw = _mul_div(10, supply + 1, total_assets + 1, true).unwrap();
+            assert_eq!(shares_for_deposit, 6);
+            assert_eq!(shares_for_withdraw, 7);
+            let assets_for_redeem = _mul_div(7, total_assets + 1, supply + 1, false).unwrap();
+            let assets_for_mint = _mul_div(7, total_assets + 1, supply + 1, true).unwrap();
+            assert_eq!(assets_for_redeem, 10);
+            assert_eq!(assets_for_mint, 11);
+        }
+
+        #[ink::test]
+        fn mul_div_overflow_is_an_error() {
+            assert_eq!(
+                _mul_div(Balance::MAX, 2, 1, false),
+                Err(WrappingError::Overflow)
+            );
+            // product fits, so large amounts still convert
+            assert_eq!(_mul_div(Balance::MAX / 2, 2, 2, true), Ok(Balance::MAX / 2));
+        }
+    }
+}
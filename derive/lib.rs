@@ -66,3 +66,9 @@ declare_derive_storage_trait!(
     SPGeneratingStorage,
     SPGeneratingStorageField
 );
+
+declare_derive_storage_trait!(
+    derive_upgrading_storage,
+    UpgradingStorage,
+    UpgradingStorageField
+);
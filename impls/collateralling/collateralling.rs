@@ -58,4 +58,9 @@ impl<T: CollaterallingStorage> CollaterallingInternal for T {
         CollaterallingStorage::get_mut(self).collateral_amount -= amount;
         Ok(())
     }
+
+    // accounts collateral transfered in by the sender (transfer and call)
+    default fn _receive_collateral(&mut self, amount: Balance) {
+        CollaterallingStorage::get_mut(self).collateral_amount += amount;
+    }
 }
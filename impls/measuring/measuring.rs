@@ -1,4 +1,5 @@
 pub use super::data::*;
+use crate::impls::upgrading::{when_migrated, UpgradingStorage};
 pub use crate::traits::measuring::*;
 pub use crate::traits::oracling::*;
 pub use crate::traits::psp22_rated::*;
@@ -13,8 +14,9 @@ const SECOND: Timestamp = 1;
 const MINUTE: Timestamp = 60 * SECOND;
 const HOUR: Timestamp = 60 * MINUTE;
 
-impl<T: MeasuringStorage + OwnableStorage> Measuring for T {
+impl<T: MeasuringStorage + OwnableStorage + UpgradingStorage> Measuring for T {
     // #[brush::modifiers(when_not_paused)] // TODO think about it
+    #[modifiers(when_migrated)]
     default fn update_stability_measure_parameter(&mut self) -> Result<u8, MeasuringError> {
         let oracle_address = MeasuringStorage::get(self).oracle_address;
         let azero_usd_price_e6 = OraclingRef::get_azero_usd_price_e6(&oracle_address);
@@ -42,6 +44,7 @@ impl<T: MeasuringStorage + OwnableStorage> Measuring for T {
         Ok(MeasuringStorage::get(self).stability_measure)
     }
 
+    #[modifiers(when_migrated)]
     #[modifiers(only_owner)]
     fn set_oracle_address(&mut self, new_oracle_address: AccountId) -> Result<(), MeasuringError> {
         MeasuringStorage::get_mut(self).oracle_address = new_oracle_address;
@@ -7,4 +7,5 @@ pub mod pausing;
 pub mod shares_profit_controlling;
 pub mod shares_profit_generating;
 pub mod stable_controlling;
+pub mod upgrading;
 pub mod vault_controlling;
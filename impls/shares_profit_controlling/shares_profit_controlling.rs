@@ -1,14 +1,22 @@
 use brush::traits::AccountId;
 
 pub use super::data::*;
+use crate::impls::upgrading::{when_migrated, UpgradingStorage};
+pub use crate::traits::savings::*;
 pub use crate::traits::shares_profit_controlling::*;
 pub use crate::traits::shares_profit_generating::*;
-use brush::{contracts::ownable::*, contracts::traits::psp22::extensions::mintable::*, modifiers};
+use brush::{
+    contracts::ownable::*, contracts::traits::psp22::extensions::mintable::*, modifiers,
+    traits::AccountIdExt,
+};
 use ink_env::CallFlags;
 
 const E6: u128 = 10_u128.pow(6);
 
-impl<T: SPControllingStorage + OwnableStorage> SPControlling for T {
+impl<T: SPControllingStorage + SPControllingInternal + OwnableStorage + UpgradingStorage>
+    SPControlling for T
+{
+    #[modifiers(when_migrated)]
     default fn collect_profit(
         &mut self,
         profit_generator: AccountId,
@@ -25,6 +33,7 @@ impl<T: SPControllingStorage + OwnableStorage> SPControlling for T {
         Ok(collected_profit)
     }
 
+    #[modifiers(when_migrated)]
     default fn distribute_income(&mut self) -> Result<(), SPControllingError> {
         let profit: i128 = SPControllingStorage::get(self).total_profit;
         if profit <= 0 {
@@ -35,23 +44,34 @@ impl<T: SPControllingStorage + OwnableStorage> SPControlling for T {
         let treassuty_address: AccountId = SPControllingStorage::get(self).treassury_address;
         let owner: AccountId = OwnableStorage::get(self).owner;
         let treassury_part_e6: u128 = SPControllingStorage::get(self).treassury_part_e6;
-        let treassury_profit: u128 = profit as u128 * treassury_part_e6 / E6;
+        let savings_address: AccountId = self._savings_address();
+        let mut income: u128 = profit as u128;
+        if !savings_address.is_zero() {
+            let savings_part: u128 =
+                SavingsRef::get_unfunded_interest(&savings_address).min(income);
+            if savings_part > 0 {
+                PSP22MintableRef::mint_builder(&stable_coin_address, savings_address, savings_part)
+                    .call_flags(CallFlags::default().set_allow_reentry(true))
+                    .fire()
+                    .unwrap()?;
+                SavingsRef::fund(&savings_address, savings_part)?;
+                income -= savings_part;
+            }
+        }
+        let treassury_profit: u128 = income * treassury_part_e6 / E6;
         PSP22MintableRef::mint_builder(&stable_coin_address, treassuty_address, treassury_profit)
             .call_flags(CallFlags::default().set_allow_reentry(true))
             .fire()
             .unwrap()?;
-        PSP22MintableRef::mint_builder(
-            &stable_coin_address,
-            owner,
-            profit as u128 - treassury_profit,
-        )
-        .call_flags(CallFlags::default().set_allow_reentry(true))
-        .fire()
-        .unwrap()?;
+        PSP22MintableRef::mint_builder(&stable_coin_address, owner, income - treassury_profit)
+            .call_flags(CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .unwrap()?;
         SPControllingStorage::get_mut(self).minted_amount += profit as u128;
         Ok(())
     }
 
+    #[modifiers(when_migrated)]
     #[modifiers(only_owner)]
     default fn set_is_generator(
         &mut self,
@@ -64,6 +84,7 @@ impl<T: SPControllingStorage + OwnableStorage> SPControlling for T {
         Ok(())
     }
 
+    #[modifiers(when_migrated)]
     #[modifiers(only_owner)]
     default fn set_treassury_address(
         &mut self,
@@ -73,6 +94,7 @@ impl<T: SPControllingStorage + OwnableStorage> SPControlling for T {
         Ok(())
     }
 
+    #[modifiers(when_migrated)]
     #[modifiers(only_owner)]
     default fn set_treassury_part_e6(
         &mut self,
@@ -85,6 +107,17 @@ impl<T: SPControllingStorage + OwnableStorage> SPControlling for T {
         Ok(())
     }
 
+    #[modifiers(when_migrated)]
+    #[modifiers(only_owner)]
+    default fn set_savings_address(
+        &mut self,
+        new_savings_address: AccountId,
+    ) -> Result<(), SPControllingError> {
+        self._set_savings_address(new_savings_address);
+        Ok(())
+    }
+
+    #[modifiers(when_migrated)]
     #[modifiers(only_owner)]
     default fn set_sharing_part_e6(
         &mut self,
@@ -103,7 +136,7 @@ impl<T: SPControllingStorage + OwnableStorage> SPControlling for T {
     }
 }
 
-impl<T: SPControllingStorage> SPControllingView for T {
+impl<T: SPControllingStorage + SPControllingInternal> SPControllingView for T {
     default fn get_stable_coin_address(&self) -> AccountId {
         SPControllingStorage::get(self).stable_coin_address.clone()
     }
@@ -126,4 +159,8 @@ impl<T: SPControllingStorage> SPControllingView for T {
     default fn get_treassury_part_e6(&self) -> u128 {
         SPControllingStorage::get(self).treassury_part_e6.clone()
     }
+
+    default fn get_savings_address(&self) -> AccountId {
+        self._savings_address()
+    }
 }
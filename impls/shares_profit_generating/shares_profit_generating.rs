@@ -1,4 +1,5 @@
 pub use super::data::*;
+use crate::impls::upgrading::{when_migrated, UpgradingStorage};
 pub use crate::traits::shares_profit_generating::*;
 use brush::{
     contracts::ownable::*,
@@ -9,8 +10,9 @@ use brush::{
 
 const E6: u128 = 10_u128.pow(6); //10^**6
 
-impl<T: SPGeneratingStorage + OwnableStorage> SPGenerating for T {
+impl<T: SPGeneratingStorage + OwnableStorage + UpgradingStorage> SPGenerating for T {
     // profiting and shares
+    #[modifiers(when_migrated)]
     default fn set_sharing_part_e6(
         &mut self,
         new_sharing_part_e6: u128,
@@ -23,6 +25,7 @@ impl<T: SPGeneratingStorage + OwnableStorage> SPGenerating for T {
     }
 
     // profiting
+    #[modifiers(when_migrated)]
     default fn give_profit(&mut self) -> Result<i128, SPGeneratingError> {
         if Self::env().caller() != SPGeneratingStorage::get(self).shares_profit_controller_address {
             return Err(SPGeneratingError::Controller);
@@ -32,6 +35,7 @@ impl<T: SPGeneratingStorage + OwnableStorage> SPGenerating for T {
         Ok(income)
     }
 
+    #[modifiers(when_migrated)]
     #[modifiers(only_owner)]
     default fn set_shares_profit_controller_address(
         &mut self,
@@ -42,6 +46,7 @@ impl<T: SPGeneratingStorage + OwnableStorage> SPGenerating for T {
     }
 
     // shares
+    #[modifiers(when_migrated)]
     default fn mint_shares(&mut self) -> Result<(), SPGeneratingError> {
         let caller = Self::env().caller();
         self._mint_shares(caller)?;
@@ -1,17 +1,20 @@
 use brush::contracts::ownable::*;
 use brush::modifiers;
-use brush::traits::AccountId;
+use brush::traits::{AccountId, AccountIdExt};
 
 pub use super::data::*;
+use crate::impls::upgrading::{when_migrated, UpgradingStorage};
 pub use crate::traits::measuring::*;
 pub use crate::traits::psp22_rated::*;
+pub use crate::traits::savings::*;
 pub use crate::traits::stable_controlling::*;
 
 const INTEREST_STEP: i128 = 318;
 
 const E6: u128 = 10_u128.pow(6);
 
-impl<T: SControllingStorage + OwnableStorage> SControlling for T {
+impl<T: SControllingStorage + OwnableStorage + UpgradingStorage> SControlling for T {
+    #[modifiers(when_migrated)]
     default fn control_stable_coin(&mut self) -> Result<(), SControllingError> {
         let measurer_address: AccountId = SControllingStorage::get(self).measurer_address;
         let stability_measure: u8 =
@@ -21,10 +24,18 @@ impl<T: SControllingStorage + OwnableStorage> SControlling for T {
         let interest_rate: i128 =
             self._stability_measure_parameter_to_interest_rate(stability_measure);
         let tax_e6 = self._ausd_usd_price_e6_to_tax_e6(ausd_usd_price_e6);
-        PSP22RatedRef::be_controlled(&stalbe_address, interest_rate, tax_e6)?;
+        let savings_address: AccountId = self._savings_address();
+        if savings_address.is_zero() {
+            PSP22RatedRef::be_controlled(&stalbe_address, interest_rate, tax_e6)?;
+        } else {
+            // negative rate of stable coin becomes savings rate, so plain balances only decrease
+            PSP22RatedRef::be_controlled(&stalbe_address, interest_rate.max(0), tax_e6)?;
+            SavingsRef::set_savings_rate_e12(&savings_address, (-interest_rate).max(0) as u128)?;
+        }
         Ok(())
     }
 
+    #[modifiers(when_migrated)]
     #[modifiers(only_owner)]
     default fn set_measurer_address(
         &mut self,
@@ -33,6 +44,16 @@ impl<T: SControllingStorage + OwnableStorage> SControlling for T {
         SControllingStorage::get_mut(self).measurer_address = new_measurer_address;
         Ok(())
     }
+
+    #[modifiers(when_migrated)]
+    #[modifiers(only_owner)]
+    default fn set_savings_address(
+        &mut self,
+        new_savings_address: AccountId,
+    ) -> Result<(), SControllingError> {
+        self._set_savings_address(new_savings_address);
+        Ok(())
+    }
 }
 
 impl<T: SControllingStorage> SControllingView for T {
@@ -43,6 +64,10 @@ impl<T: SControllingStorage> SControllingView for T {
     default fn get_measurer_address(&mut self) -> AccountId {
         SControllingStorage::get(self).measurer_address
     }
+
+    default fn get_savings_address(&mut self) -> AccountId {
+        self._savings_address()
+    }
 }
 
 impl<T: SControllingStorage> SControllingInternal for T {
@@ -60,4 +85,11 @@ impl<T: SControllingStorage> SControllingInternal for T {
             _ => (ausd_usd_price_e6 - 1005000) * E6 / ausd_usd_price_e6,
         }
     }
+
+    // zero address keeps savings off, contracts with savings override these with their own storage
+    default fn _savings_address(&self) -> AccountId {
+        AccountId::from([0; 32])
+    }
+
+    default fn _set_savings_address(&mut self, _savings_address: AccountId) {}
 }
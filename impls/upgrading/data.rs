@@ -0,0 +1,21 @@
+// importing everything publicly from traits allows you to import every stuff related to upgrading
+// by one import
+pub use crate::traits::upgrading::*;
+use brush::declare_storage_trait;
+
+use ink_storage::traits::{SpreadAllocate, SpreadLayout};
+// it is public because when you will import the trait you also will import the derive for the trait
+pub use stable_coin_project_derive::UpgradingStorage;
+
+#[cfg(feature = "std")]
+use ink_storage::traits::StorageLayout;
+#[derive(Default, Debug, SpreadAllocate, SpreadLayout)]
+#[cfg_attr(feature = "std", derive(StorageLayout))]
+/// define the struct with the data that our smart contract will be using
+/// this will isolate the logic of our smart contract from its storage
+pub struct UpgradingData {
+    pub storage_version: u32, // version of storage layout, raised by migrate
+    pub code_hash: Option<[u8; 32]>, // set by upgrade, None until the first upgrade
+}
+
+declare_storage_trait!(UpgradingStorage, UpgradingData);
@@ -0,0 +1,4 @@
+mod data;
+mod upgrading;
+
+pub use upgrading::*;
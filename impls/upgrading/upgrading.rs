@@ -0,0 +1,73 @@
+pub use super::data::*;
+use brush::{contracts::ownable::*, modifier_definition, modifiers};
+
+/// Runs pending migrations before the body, so the first state changing call after an upgrade works on migrated storage.
+/// Migration failure traps, which reverts the whole call (returning an error would keep half migrated storage).
+#[modifier_definition]
+pub fn when_migrated<T, F, R>(instance: &mut T, body: F) -> R
+where
+    T: UpgradingStorage,
+    F: FnOnce(&mut T) -> R,
+{
+    instance
+        ._ensure_migrated()
+        .expect("storage migration failed");
+    body(instance)
+}
+
+impl<T: UpgradingStorage + OwnableStorage> Upgrading for T {
+    #[modifiers(only_owner)]
+    default fn upgrade(&mut self, code_hash: [u8; 32]) -> Result<(), UpgradingError> {
+        ink_env::set_code_hash(&code_hash).map_err(|_| UpgradingError::CodeHashNotSet)?;
+        let old_code_hash = UpgradingStorage::get(self).code_hash;
+        UpgradingStorage::get_mut(self).code_hash = Some(code_hash);
+        self._emit_upgraded_event(old_code_hash, code_hash);
+        Ok(())
+    }
+
+    default fn migrate(&mut self) -> Result<(), UpgradingError> {
+        self._ensure_migrated()
+    }
+
+    default fn get_storage_version(&self) -> u32 {
+        UpgradingStorage::get(self).storage_version
+    }
+
+    default fn get_code_hash(&self) -> Option<[u8; 32]> {
+        UpgradingStorage::get(self).code_hash
+    }
+}
+
+impl<T: UpgradingStorage> UpgradingInternal for T {
+    default fn _code_storage_version(&self) -> u32 {
+        UpgradingStorage::get(self).storage_version
+    }
+
+    default fn _migrate(&mut self, _from_version: u32) -> Result<(), UpgradingError> {
+        Ok(())
+    }
+
+    default fn _ensure_migrated(&mut self) -> Result<(), UpgradingError> {
+        let from_version = UpgradingStorage::get(self).storage_version;
+        let to_version = self._code_storage_version();
+        if from_version > to_version {
+            return Err(UpgradingError::StorageVersionAboveCode);
+        }
+        if from_version == to_version {
+            return Ok(());
+        }
+        self._migrate(from_version)?;
+        UpgradingStorage::get_mut(self).storage_version = to_version;
+        self._emit_migrated_event(from_version, to_version);
+        Ok(())
+    }
+
+    default fn _emit_upgraded_event(
+        &self,
+        _old_code_hash: Option<[u8; 32]>,
+        _new_code_hash: [u8; 32],
+    ) {
+    }
+
+    default fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32) {}
+}
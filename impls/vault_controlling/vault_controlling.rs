@@ -1,10 +1,13 @@
 pub use super::data::*;
+use crate::impls::upgrading::{when_migrated, UpgradingStorage};
 pub use crate::traits::measuring::*;
 pub use crate::traits::vault::*;
 pub use crate::traits::vault_controlling::*;
+use brush::modifiers;
 use brush::traits::AccountId;
 
-impl<T: VControllingStorage> VControlling for T {
+impl<T: VControllingStorage + UpgradingStorage> VControlling for T {
+    #[modifiers(when_migrated)]
     default fn control_vault(&mut self) -> Result<(), VControllingError> {
         let measurer_address = VControllingStorage::get(self).measurer_address;
         let stability_measure =
@@ -2,6 +2,7 @@
 #![feature(min_specialization)]
 
 pub mod impls;
+pub mod math;
 pub mod traits;
 pub use stable_coin_project_derive::CollaterallingStorage;
 pub use stable_coin_project_derive::EatingStorage;
@@ -9,5 +10,6 @@ pub use stable_coin_project_derive::EmittingStorage;
 pub use stable_coin_project_derive::SControllingStorage;
 pub use stable_coin_project_derive::SPControllingStorage;
 pub use stable_coin_project_derive::SPGeneratingStorage;
+pub use stable_coin_project_derive::UpgradingStorage;
 pub use stable_coin_project_derive::VControllingStorage;
 pub use stable_coin_project_derive::VEatingStorage;
@@ -0,0 +1,19 @@
+use brush::traits::Timestamp;
+
+pub const E12: u128 = 10_u128.pow(12);
+
+/// base_e12^exponent in e12 fixed point, exponentiation by squaring.
+/// Intermediate products saturate, so result is u128::MAX / E12 at most instead of overflowing.
+pub fn rpow_e12(mut base_e12: u128, mut exponent: Timestamp) -> u128 {
+    let mut result_e12: u128 = E12;
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result_e12 = result_e12.saturating_mul(base_e12) / E12;
+        }
+        exponent /= 2;
+        if exponent > 0 {
+            base_e12 = base_e12.saturating_mul(base_e12) / E12;
+        }
+    }
+    result_e12
+}
@@ -99,6 +99,44 @@ const types = {
       OwnableError: 'ContractsErrorsOwnableOwnableError',
       MeasuringError: 'StableCoinProjectMeasuringMeasuringError',
       PSP22Error: 'ContractsErrorsPsp22Psp22Error',
+      SavingsError: 'StableCoinProjectSavingsSavingsError',
+    },
+  },
+
+  StableCoinProjectWrappingWrappingError: {
+    _enum: {
+      ZeroAmount: null,
+      AmountAboveMaximum: null,
+      NotTaxFree: null,
+      PSP22Error: 'ContractsErrorsPsp22Psp22Error',
+      Overflow: null,
+      InsufficientAssetsReceived: null,
+    },
+  },
+
+  StableCoinProjectReportingReportingError: {
+    _enum: {
+      VaultAlreadyAdded: null,
+      VaultNotAdded: null,
+      OwnableError: 'ContractsErrorsOwnableOwnableError',
+    },
+  },
+
+  StableCoinProjectMintLimitingMintLimitError: {
+    _enum: {
+      QuotaExceeded: null,
+      OwnableError: 'ContractsErrorsOwnableOwnableError',
+    },
+  },
+
+  StableCoinProjectSavingsSavingsError: {
+    _enum: {
+      InsufficientSavings: null,
+      NotFunded: null,
+      StableController: null,
+      SharesProfitController: null,
+      OwnableError: 'ContractsErrorsOwnableOwnableError',
+      PSP22Error: 'ContractsErrorsPsp22Psp22Error',
     },
   },
 
@@ -118,6 +156,7 @@ const types = {
       PSP22Error: 'ContractsErrorsPsp22Psp22Error',
       OwnableError: 'ContractsErrorsOwnableOwnableError',
       SPGeneratingError: 'StableCoinProjectSPGeneratingSPGeneratingError',
+      SavingsError: 'StableCoinProjectSavingsSavingsError',
     },
   },
 
@@ -132,12 +171,31 @@ const types = {
       VaultOwnership: null,
       CollateralBelowMinimum: null,
       CollateralAboveMinimum: null,
+      DebtBelowMinimum: null,
+      TotalDebtAboveMaximum: null,
+      EmitedSupplyAboveMaximum: null,
+      Liquidator: null,
+      DebtNotAccepted: null,
+      SameVault: null,
+      AccountFrozen: null,
       PSP22Error: 'ContractsErrorsPsp22Psp22Error',
       PSP34Error: 'ContractsErrorsPsp34Psp34Error',
       PausableError: 'ContractsErrorsPausablePausableError',
       OwnableError: 'ContractsErrorsOwnableOwnableError',
       CollaterallingError: 'StableCoinProjectCollaterallingCollaterallingError',
       EmittingError: 'StableCoinProjectEmitingEmitingError',
+      SwapFailed: null,
+      SwapAdapterError: 'StableCoinProjectSwapAdapterSwapAdapterError',
+    },
+  },
+
+  StableCoinProjectSwapAdapterSwapAdapterError: {
+    _enum: {
+      UnsupportedPair: null,
+      InsufficientInputAmount: null,
+      InsufficientOutputAmount: null,
+      InsufficientLiquidity: null,
+      PSP22Error: 'ContractsErrorsPsp22Psp22Error',
     },
   },
   ContractsDiamondFacetCut: {
@@ -1,3 +1,4 @@
+import { network } from 'redspot';
 import { AccountId } from '@polkadot/types/interfaces';
 import { expect, fromSigner, setupContract } from './helpers';
 import { consts } from './constants';
@@ -57,12 +58,41 @@ export async function deployStableController(measurerAddress: string, stableAddr
   return ret;
 }
 
+export async function deploySavings(
+  stableAddress: string,
+  stableControllerAddress: string,
+  sharesProfitControllerAddress: string,
+  owner: string,
+) {
+  const ret = await setupContract('savings_contract', 'new', stableAddress, stableControllerAddress, sharesProfitControllerAddress, owner);
+  console.log(`deploy savings_contract : at ${ret.contract.address.toString()}`);
+  return ret;
+}
+
+export async function deployWrappedStableCoin(stableAddress: string, owner: string) {
+  const ret = await setupContract('wrapped_stable_coin_contract', 'new', 'Wrapped aUSD', 'waUSD', consts.STABLE_DECIMALS, stableAddress, owner);
+  console.log(`deploy wrapped_stable_coin_contract : at ${ret.contract.address.toString()}`);
+  return ret;
+}
+
+export async function deployReporting(stableAddress: string, sharesProfitControllerAddress: string, owner: string) {
+  const ret = await setupContract('reporting_contract', 'new', stableAddress, sharesProfitControllerAddress, owner);
+  console.log(`deploy reporting_contract : at ${ret.contract.address.toString()}`);
+  return ret;
+}
+
 export async function deployCollateralMock(decimals: number = consts.COLLATERAL_DECIMALS, owner: string) {
   const ret = await setupContract('psp22_emitable_contract', 'new', 'emitable_coin', 'sample_description', decimals, owner);
   console.log(`deploy psp22_emitable_contract : at ${ret.contract.address.toString()}`);
   return ret;
 }
 
+export async function deploySwapAdapterMock(tokenAAddress: string, tokenBAddress: string) {
+  const ret = await setupContract('swap_adapter_mock_contract', 'new', tokenAAddress, tokenBAddress);
+  console.log(`deploy swap_adapter_mock_contract : at ${ret.contract.address.toString()}`);
+  return ret;
+}
+
 export async function deployVault(
   oracleAddress: string,
   sharesTokenAddress: string,
@@ -114,6 +144,7 @@ export async function setupStableCoinContract(
   );
 
   await fromSigner(stableCoinResults.contract, owner).tx.setStableControllerAddress(stableControllerResults.contract.address.toString());
+  await fromSigner(stableCoinResults.contract, owner).tx.setChainId(network.api.genesisHash.toU8a());
   await fromSigner(sharesContract, owner).tx.setupRole(consts.MINTER, stableCoinResults.contract.address.toString());
   console.log('setup_stabe END');
   return { stableCoin: stableCoinResults, stableController: stableControllerResults };
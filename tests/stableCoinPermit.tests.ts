@@ -0,0 +1,83 @@
+import { network } from 'redspot';
+import { Keyring } from '@polkadot/keyring';
+import { KeyringPair } from '@polkadot/keyring/types';
+import { cryptoWaitReady } from '@polkadot/util-crypto';
+import { expect, fromSigner } from '../scripts/helpers';
+import { Signer } from 'redspot/types';
+import Contract from '@redspot/patract/contract';
+import { deploySystem } from '../scripts/ourDeployRated';
+const { getSigners, api } = network;
+
+const NO_DEADLINE: bigint = BigInt('18446744073709551615');
+
+// owner signs blake2x256 of SCALE encoded (domain_separator, owner, spender, value, nonce, deadline)
+async function signPermit(
+  stableCoinContract: Contract,
+  signer: KeyringPair,
+  owner: string,
+  spender: string,
+  value: bigint,
+  nonce: number,
+  deadline: bigint
+): Promise<Uint8Array> {
+  const domainSeparator = (await stableCoinContract.query.domainSeparator()).output?.toU8a() as Uint8Array;
+  const payload = api
+    .createType('(H256, AccountId, AccountId, u128, u64, u64)', [domainSeparator, owner, spender, value, nonce, deadline])
+    .toU8a();
+  // ecdsa pair hashes the payload with blake2 before signing, signature is (r, s, recovery id)
+  return signer.sign(payload);
+}
+
+describe('StableCoin permit', () => {
+  const VALUE: bigint = BigInt('1000000');
+  let users: Signer[];
+  let owner: Signer;
+  let stableCoinContract: Contract;
+  let permitOwner: KeyringPair;
+  let otherSigner: KeyringPair;
+
+  beforeEach('setup system', async () => {
+    await cryptoWaitReady();
+    users = await getSigners();
+    owner = users.shift() as Signer;
+    const contracts = await deploySystem(owner);
+    stableCoinContract = contracts.stableCoinContract;
+    const keyring = new Keyring({ type: 'ecdsa' });
+    permitOwner = keyring.addFromUri('//PermitOwner');
+    otherSigner = keyring.addFromUri('//OtherSigner');
+  });
+
+  it('permit approves spender and uses the nonce', async () => {
+    const signature = await signPermit(stableCoinContract, permitOwner, permitOwner.address, users[1].address, VALUE, 0, NO_DEADLINE);
+    await expect(
+      fromSigner(stableCoinContract, users[0].address).tx.permit(permitOwner.address, users[1].address, VALUE, NO_DEADLINE, signature)
+    ).to.eventually.be.fulfilled;
+    await expect(stableCoinContract.query.allowance(permitOwner.address, users[1].address)).to.have.output(VALUE);
+    await expect(stableCoinContract.query.nonces(permitOwner.address)).to.have.output(1);
+  });
+
+  it('permit can not be replayed', async () => {
+    const signature = await signPermit(stableCoinContract, permitOwner, permitOwner.address, users[1].address, VALUE, 0, NO_DEADLINE);
+    await fromSigner(stableCoinContract, users[0].address).tx.permit(permitOwner.address, users[1].address, VALUE, NO_DEADLINE, signature);
+    await expect(
+      fromSigner(stableCoinContract, users[0].address).tx.permit(permitOwner.address, users[1].address, VALUE, NO_DEADLINE, signature)
+    ).to.eventually.be.rejected;
+    await expect(stableCoinContract.query.nonces(permitOwner.address)).to.have.output(1);
+  });
+
+  it('permit signed by other account is refused', async () => {
+    const signature = await signPermit(stableCoinContract, otherSigner, permitOwner.address, users[1].address, VALUE, 0, NO_DEADLINE);
+    await expect(
+      fromSigner(stableCoinContract, users[0].address).tx.permit(permitOwner.address, users[1].address, VALUE, NO_DEADLINE, signature)
+    ).to.eventually.be.rejected;
+    await expect(stableCoinContract.query.allowance(permitOwner.address, users[1].address)).to.have.output(0);
+  });
+
+  it('expired permit is refused', async () => {
+    const signature = await signPermit(stableCoinContract, permitOwner, permitOwner.address, users[1].address, VALUE, 0, 0n);
+    await expect(
+      fromSigner(stableCoinContract, users[0].address).tx.permit(permitOwner.address, users[1].address, VALUE, 0n, signature)
+    ).to.eventually.be.rejected;
+    await expect(stableCoinContract.query.nonces(permitOwner.address)).to.have.output(0);
+  });
+});
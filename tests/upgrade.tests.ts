@@ -0,0 +1,69 @@
+import { network } from 'redspot';
+import { expect, fromSigner, setupContract, setupProxy } from '../scripts/helpers';
+import { Signer } from 'redspot/types';
+import Contract from '@redspot/patract/contract';
+import { deploySystem } from '../scripts/ourDeployRated';
+const { getSigners } = network;
+
+// code of the first upgradable vault (storage version 1), built with:
+// npm run cs -- vault_contract --rev e616bd9 --as vault_contract_v1
+const OLD_VAULT_ARTIFACT = 'vault_contract_v1';
+
+describe('Upgrade', () => {
+  const MINTED_AMOUNT: bigint = BigInt('4313514311412321412');
+  const DEPOSITED_AMOUNT: bigint = BigInt('1000000000000');
+  let users: Signer[];
+  let owner: Signer;
+  let collateralTokenContract: Contract;
+  let vaultContract: Contract;
+  let oldVaultContract: Contract;
+  let newCodeHash: string;
+
+  beforeEach('deploy system and vault with old code', async () => {
+    users = await getSigners();
+    owner = users.shift() as Signer;
+    // deploys current vault, so its code is on chain
+    const contracts = await deploySystem(owner);
+    collateralTokenContract = contracts.collateralTokenContract;
+    vaultContract = contracts.vaultContract;
+    newCodeHash = vaultContract.abi.info.source.wasmHash.toHex();
+
+    const { contract } = await setupContract(
+      OLD_VAULT_ARTIFACT,
+      'new',
+      contracts.oracleContract.address.toString(),
+      contracts.sharesContract.address.toString(),
+      contracts.sharesProfitControllerContract.address.toString(),
+      collateralTokenContract.address.toString(),
+      contracts.stableCoinContract.address.toString(),
+      2000000,
+      10000,
+      0,
+      owner.address
+    );
+    oldVaultContract = contract;
+    await fromSigner(collateralTokenContract, users[0].address).tx.mintAnyCaller(users[0].address, MINTED_AMOUNT);
+    await fromSigner(collateralTokenContract, users[0].address).tx.approve(oldVaultContract.address, MINTED_AMOUNT);
+    await fromSigner(oldVaultContract, users[0].address).tx.createVault();
+    await fromSigner(oldVaultContract, users[0].address).tx.depositCollateral(0, DEPOSITED_AMOUNT);
+  });
+
+  it('upgraded vault keeps its state and migrates on the first message', async () => {
+    await expect(fromSigner(oldVaultContract, owner.address).tx.upgrade(newCodeHash)).to.eventually.be.fulfilled;
+    // the same contract called through abi of the new code
+    const upgradedVaultContract = setupProxy(vaultContract, oldVaultContract);
+    await expect(fromSigner(upgradedVaultContract, users[0].address).tx.createVault()).to.eventually.be.fulfilled;
+    await expect(upgradedVaultContract.query.getStorageVersion()).to.have.output(4);
+    await expect(upgradedVaultContract.query.getVaultDetails(0)).to.have.output([DEPOSITED_AMOUNT, 0]);
+    await expect(upgradedVaultContract.query.vaultsOf(users[0].address, 0, 10)).to.have.output([0, 1]);
+    await expect(upgradedVaultContract.query.getFlashFeeCollateral()).to.have.output(0);
+    await expect(fromSigner(upgradedVaultContract, users[0].address).tx.withdrawCollateral(0, DEPOSITED_AMOUNT)).to.eventually.be
+      .fulfilled;
+    await expect(collateralTokenContract.query.balanceOf(users[0].address)).to.have.output(MINTED_AMOUNT);
+  });
+
+  it('only owner upgrades', async () => {
+    await expect(fromSigner(oldVaultContract, users[0].address).tx.upgrade(newCodeHash)).to.eventually.be.rejected;
+    await expect(oldVaultContract.query.getStorageVersion()).to.have.output(1);
+  });
+});
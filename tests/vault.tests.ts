@@ -4,11 +4,18 @@ import { consts } from '../scripts/constants';
 import { Signer } from 'redspot/types';
 import Contract from '@redspot/patract/contract';
 import { deploySystem } from '../scripts/ourDeployRated';
+import { deploySwapAdapterMock } from '../scripts/ourHelpers';
 const { getSigners, api } = network;
 
 const E6: bigint = 1000000n;
 const STA_DEC: bigint = E6;
 const COL_DEC: bigint = E6 * E6;
+
+async function getVaultDetails(vaultContract: Contract, vaultId: number): Promise<[bigint, bigint]> {
+  const output = (await vaultContract.query.getVaultDetails(vaultId)).output as any;
+  return [BigInt(output[0].toString()), BigInt(output[1].toString())];
+}
+
 describe('Vault', () => {
   let users: Signer[];
   let owner: Signer;
@@ -31,7 +38,7 @@ describe('Vault', () => {
     vaultControllerContract = contracts.vaultControllerContract;
   });
 
-  describe.only('vaults creation and destruction', async () => {
+  describe('vaults creation and destruction', async () => {
     it('owner creates a vault and mints an nft', async () => {
       await expect(fromSigner(vaultContract, owner.address).tx.createVault()).to.eventually.be.fulfilled;
       await expect(vaultContract.query.totalSupply()).to.have.output(1);
@@ -154,4 +161,162 @@ describe('Vault', () => {
       await expect(vaultContract.query.getVaultDetails(0)).to.have.output([DEPOSITED_AMOUNT, debtCeiling - 1n]);
     });
   });
+
+  describe('repay', async () => {
+    const MINTED_AMOUNT: bigint = BigInt('4313514311412321412');
+    const DEPOSITED_AMOUNT: bigint = BigInt('1000000000000');
+    const BORROWED_AMOUNT: bigint = BigInt('100000');
+    const REPAID_AMOUNT: bigint = BigInt('40000');
+    beforeEach('create vault, borrow and give stable coin to other account', async () => {
+      await fromSigner(vaultContract, users[0].address).tx.createVault();
+      await fromSigner(collateralTokenContract, users[0].address).tx.mintAnyCaller(users[0].address, MINTED_AMOUNT);
+      await fromSigner(collateralTokenContract, users[0].address).tx.approve(vaultContract.address, MINTED_AMOUNT);
+      await fromSigner(vaultContract, users[0].address).tx.depositCollateral(0, DEPOSITED_AMOUNT);
+      await fromSigner(vaultContract, users[0].address).tx.borrowToken(0, BORROWED_AMOUNT);
+      await fromSigner(stableCoinContract, owner.address).tx.setupRole(consts.MINTER, owner.address);
+      await fromSigner(stableCoinContract, owner.address).tx.mint(users[1].address, BORROWED_AMOUNT);
+    });
+
+    it('any account repays debt of other owners vault with its own tokens', async () => {
+      await expect(fromSigner(vaultContract, users[1].address).tx.repay(0, REPAID_AMOUNT)).to.eventually.be.fulfilled;
+      await expect(vaultContract.query.getVaultDetails(0)).to.have.output([DEPOSITED_AMOUNT, BORROWED_AMOUNT - REPAID_AMOUNT]);
+      await expect(vaultContract.query.getTotalDebt()).to.have.output(BORROWED_AMOUNT - REPAID_AMOUNT);
+      await expect(stableCoinContract.query.accountDebt(users[0].address)).to.have.output(BORROWED_AMOUNT - REPAID_AMOUNT);
+      await expect(stableCoinContract.query.balanceOf(users[1].address)).to.have.output(BORROWED_AMOUNT - REPAID_AMOUNT);
+      await expect(stableCoinContract.query.balanceOf(users[0].address)).to.have.output(BORROWED_AMOUNT);
+    });
+
+    it('repay above debt burns only the debt', async () => {
+      await fromSigner(stableCoinContract, owner.address).tx.mint(users[1].address, BORROWED_AMOUNT);
+      await expect(fromSigner(vaultContract, users[1].address).tx.repay(0, 2n * BORROWED_AMOUNT)).to.eventually.be.fulfilled;
+      await expect(vaultContract.query.getVaultDetails(0)).to.have.output([DEPOSITED_AMOUNT, 0]);
+      await expect(stableCoinContract.query.accountDebt(users[0].address)).to.have.output(0);
+      await expect(stableCoinContract.query.balanceOf(users[1].address)).to.have.output(BORROWED_AMOUNT);
+    });
+
+    it('repay fails if payer has not enough tokens', async () => {
+      await expect(fromSigner(vaultContract, users[2].address).tx.repay(0, REPAID_AMOUNT)).to.eventually.be.rejected;
+      await expect(vaultContract.query.getVaultDetails(0)).to.have.output([DEPOSITED_AMOUNT, BORROWED_AMOUNT]);
+    });
+  });
+
+  describe('merge and split', async () => {
+    const MINTED_AMOUNT: bigint = BigInt('4313514311412321412');
+    const DEPOSITED_AMOUNT: bigint = BigInt('1000000000000');
+    const BORROWED_AMOUNT: bigint = BigInt('100000');
+    beforeEach('create two vaults with collateral and debt', async () => {
+      await fromSigner(collateralTokenContract, users[0].address).tx.mintAnyCaller(users[0].address, MINTED_AMOUNT);
+      await fromSigner(collateralTokenContract, users[0].address).tx.approve(vaultContract.address, MINTED_AMOUNT);
+      for (const id of [0, 1]) {
+        await fromSigner(vaultContract, users[0].address).tx.createVault();
+        await fromSigner(vaultContract, users[0].address).tx.depositCollateral(id, DEPOSITED_AMOUNT);
+        await fromSigner(vaultContract, users[0].address).tx.borrowToken(id, BORROWED_AMOUNT);
+      }
+    });
+
+    it('merge moves collateral and debt and burns the source vault', async () => {
+      await expect(fromSigner(vaultContract, users[0].address).tx.mergeVaults(0, 1)).to.eventually.be.fulfilled;
+      await expect(vaultContract.query.getVaultDetails(0)).to.have.output([2n * DEPOSITED_AMOUNT, 2n * BORROWED_AMOUNT]);
+      await expect(vaultContract.query.getVaultDetails(1)).to.have.output([0, 0]);
+      await expect(vaultContract.query.totalSupply()).to.have.output(1);
+      await expect(vaultContract.query.getTotalDebt()).to.have.output(2n * BORROWED_AMOUNT);
+      await expect(stableCoinContract.query.accountDebt(users[0].address)).to.have.output(2n * BORROWED_AMOUNT);
+      await expect(collateralTokenContract.query.balanceOf(vaultContract.address)).to.have.output(2n * DEPOSITED_AMOUNT);
+    });
+
+    it('merge of vaults of other owner fails', async () => {
+      await expect(fromSigner(vaultContract, users[1].address).tx.mergeVaults(0, 1)).to.eventually.be.rejected;
+      await fromSigner(vaultContract, users[1].address).tx.createVault();
+      await expect(fromSigner(vaultContract, users[1].address).tx.mergeVaults(2, 0)).to.eventually.be.rejected;
+      await expect(vaultContract.query.getVaultDetails(0)).to.have.output([DEPOSITED_AMOUNT, BORROWED_AMOUNT]);
+    });
+
+    it('split carves position out to a new vault of the owner', async () => {
+      await expect(fromSigner(vaultContract, users[0].address).tx.splitVault(0, DEPOSITED_AMOUNT / 4n, BORROWED_AMOUNT / 2n)).to
+        .eventually.be.fulfilled;
+      await expect(vaultContract.query.ownerOf({ u128: 2 })).to.have.output(users[0].address);
+      await expect(vaultContract.query.getVaultDetails(0)).to.have.output([
+        DEPOSITED_AMOUNT - DEPOSITED_AMOUNT / 4n,
+        BORROWED_AMOUNT - BORROWED_AMOUNT / 2n,
+      ]);
+      await expect(vaultContract.query.getVaultDetails(2)).to.have.output([DEPOSITED_AMOUNT / 4n, BORROWED_AMOUNT / 2n]);
+      await expect(vaultContract.query.getTotalDebt()).to.have.output(2n * BORROWED_AMOUNT);
+      await expect(stableCoinContract.query.accountDebt(users[0].address)).to.have.output(2n * BORROWED_AMOUNT);
+    });
+
+    it('split that leaves a side below minimum collateral ratio fails', async () => {
+      await expect(fromSigner(vaultContract, users[0].address).tx.splitVault(0, DEPOSITED_AMOUNT, 0)).to.eventually.be.rejected;
+      await expect(fromSigner(vaultContract, users[0].address).tx.splitVault(0, 0, BORROWED_AMOUNT)).to.eventually.be.rejected;
+      await expect(vaultContract.query.getVaultDetails(0)).to.have.output([DEPOSITED_AMOUNT, BORROWED_AMOUNT]);
+      await expect(vaultContract.query.totalSupply()).to.have.output(2);
+    });
+  });
+
+  describe('leverage', async () => {
+    const MINTED_AMOUNT: bigint = BigInt('4313514311412321412');
+    const AZERO_USD_PRICE: bigint = BigInt('1200000');
+    const DEPOSITED_AMOUNT: bigint = COL_DEC;
+    // deep pool at oracle price, so slippage of one swap stays small
+    const POOL_COLLATERAL: bigint = 1000n * COL_DEC;
+    const POOL_STABLE: bigint = 1000n * AZERO_USD_PRICE;
+    let swapAdapterContract: Contract;
+
+    async function getCollateralRatioE6(): Promise<bigint> {
+      const [collateral, debt] = await getVaultDetails(vaultContract, 0);
+      return (collateral * AZERO_USD_PRICE * STA_DEC * E6) / (COL_DEC * debt);
+    }
+
+    beforeEach('create vault and fill pool', async () => {
+      const { contract } = await deploySwapAdapterMock(
+        collateralTokenContract.address.toString(),
+        stableCoinContract.address.toString()
+      );
+      swapAdapterContract = contract;
+      await fromSigner(vaultContract, owner.address).tx.setSwapAdapterAddress(swapAdapterContract.address);
+      await fromSigner(collateralTokenContract, owner.address).tx.mintAnyCaller(swapAdapterContract.address, POOL_COLLATERAL);
+      await fromSigner(stableCoinContract, owner.address).tx.setupRole(consts.MINTER, owner.address);
+      await fromSigner(stableCoinContract, owner.address).tx.mint(swapAdapterContract.address, POOL_STABLE);
+      await fromSigner(swapAdapterContract, owner.address).tx.sync();
+
+      await fromSigner(oracleContract, owner.address).tx.feedAzeroUsdPriceE6(AZERO_USD_PRICE);
+      await fromSigner(vaultContract, users[0].address).tx.createVault();
+      await fromSigner(collateralTokenContract, users[0].address).tx.mintAnyCaller(users[0].address, MINTED_AMOUNT);
+      await fromSigner(collateralTokenContract, users[0].address).tx.approve(vaultContract.address, MINTED_AMOUNT);
+      await fromSigner(vaultContract, users[0].address).tx.depositCollateral(0, DEPOSITED_AMOUNT);
+    });
+
+    it('leverage borrows and deposits swapped collateral down to target ratio', async () => {
+      const TARGET_RATIO_E6: bigint = 2500000n;
+      await expect(fromSigner(vaultContract, users[0].address).tx.leverage(0, TARGET_RATIO_E6, 0)).to.eventually.be.fulfilled;
+      const [collateral, debt] = await getVaultDetails(vaultContract, 0);
+      expect(collateral > DEPOSITED_AMOUNT).to.be.true;
+      await expect(stableCoinContract.query.accountDebt(users[0].address)).to.have.output(debt);
+      // sized by oracle price, slippage leaves ratio at most 1% below target
+      const ratio = await getCollateralRatioE6();
+      expect(ratio <= TARGET_RATIO_E6).to.be.true;
+      expect(ratio >= (TARGET_RATIO_E6 * 99n) / 100n).to.be.true;
+    });
+
+    it('deleverage sells collateral and repays debt up to target ratio', async () => {
+      const TARGET_RATIO_E6: bigint = 4000000n;
+      await fromSigner(vaultContract, users[0].address).tx.leverage(0, 2500000n, 0);
+      const [, debtBefore] = await getVaultDetails(vaultContract, 0);
+      await expect(fromSigner(vaultContract, users[0].address).tx.deleverage(0, TARGET_RATIO_E6, 0)).to.eventually.be.fulfilled;
+      const [, debt] = await getVaultDetails(vaultContract, 0);
+      expect(debt < debtBefore).to.be.true;
+      const ratio = await getCollateralRatioE6();
+      expect(ratio >= (TARGET_RATIO_E6 * 99n) / 100n).to.be.true;
+      expect(ratio <= (TARGET_RATIO_E6 * 101n) / 100n).to.be.true;
+    });
+
+    it('leverage below minimum collateral ratio fails', async () => {
+      await expect(fromSigner(vaultContract, users[0].address).tx.leverage(0, 1900000n, 0)).to.eventually.be.rejected;
+      await expect(vaultContract.query.getVaultDetails(0)).to.have.output([DEPOSITED_AMOUNT, 0]);
+    });
+
+    it('leverage fails if swaps give less than minimum output', async () => {
+      await expect(fromSigner(vaultContract, users[0].address).tx.leverage(0, 2500000n, DEPOSITED_AMOUNT)).to.eventually.be.rejected;
+      await expect(vaultContract.query.getVaultDetails(0)).to.have.output([DEPOSITED_AMOUNT, 0]);
+    });
+  });
 });
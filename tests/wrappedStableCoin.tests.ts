@@ -0,0 +1,81 @@
+import { network } from 'redspot';
+import { expect, fromSigner } from '../scripts/helpers';
+import { consts } from '../scripts/constants';
+import { Signer } from 'redspot/types';
+import Contract from '@redspot/patract/contract';
+import { deploySystem } from '../scripts/ourDeployRated';
+import { deployWrappedStableCoin } from '../scripts/ourHelpers';
+const { getSigners } = network;
+
+describe('WrappedStableCoin', () => {
+  const MINTED_AMOUNT: bigint = BigInt('1000000000');
+  const DEPOSITED_AMOUNT: bigint = BigInt('100000000');
+  let users: Signer[];
+  let owner: Signer;
+  let stableCoinContract: Contract;
+  let wrappedContract: Contract;
+
+  beforeEach('setup system and wrapper', async () => {
+    users = await getSigners();
+    owner = users.shift() as Signer;
+    const contracts = await deploySystem(owner);
+    stableCoinContract = contracts.stableCoinContract;
+    const { contract } = await deployWrappedStableCoin(stableCoinContract.address.toString(), owner.address);
+    wrappedContract = contract;
+
+    await fromSigner(stableCoinContract, owner.address).tx.setupRole(consts.MINTER, owner.address);
+    await fromSigner(stableCoinContract, owner.address).tx.mint(users[0].address, MINTED_AMOUNT);
+    await fromSigner(stableCoinContract, users[0].address).tx.approve(wrappedContract.address, MINTED_AMOUNT);
+  });
+
+  describe('tax free wrapper', async () => {
+    beforeEach('set wrapper tax free', async () => {
+      await fromSigner(stableCoinContract, owner.address).tx.setIsTaxFree(wrappedContract.address, true);
+    });
+
+    it('deposit mints shares for received assets', async () => {
+      const expectedShares = BigInt((await wrappedContract.query.previewDeposit(DEPOSITED_AMOUNT)).output?.toString() as string);
+      await expect(fromSigner(wrappedContract, users[0].address).tx.deposit(DEPOSITED_AMOUNT, users[0].address)).to.eventually.be
+        .fulfilled;
+      await expect(wrappedContract.query.balanceOf(users[0].address)).to.have.output(expectedShares);
+      await expect(wrappedContract.query.totalAssets()).to.have.output(DEPOSITED_AMOUNT);
+      await expect(stableCoinContract.query.balanceOf(users[0].address)).to.have.output(MINTED_AMOUNT - DEPOSITED_AMOUNT);
+    });
+
+    it('redeem of all shares gives deposited assets back', async () => {
+      await fromSigner(wrappedContract, users[0].address).tx.deposit(DEPOSITED_AMOUNT, users[0].address);
+      const shares = BigInt((await wrappedContract.query.balanceOf(users[0].address)).output?.toString() as string);
+      await expect(fromSigner(wrappedContract, users[0].address).tx.redeem(shares, users[0].address, users[0].address)).to.eventually
+        .be.fulfilled;
+      await expect(wrappedContract.query.balanceOf(users[0].address)).to.have.output(0);
+      await expect(stableCoinContract.query.balanceOf(users[0].address)).to.have.output(MINTED_AMOUNT);
+    });
+
+    it('redeem of shares of other owner needs allowance', async () => {
+      await fromSigner(wrappedContract, users[0].address).tx.deposit(DEPOSITED_AMOUNT, users[0].address);
+      await expect(fromSigner(wrappedContract, users[1].address).tx.redeem(1, users[1].address, users[0].address)).to.eventually.be
+        .rejected;
+    });
+
+    it('mint takes rounding slack and keeps shares backed', async () => {
+      const assets = BigInt((await wrappedContract.query.previewMint(DEPOSITED_AMOUNT)).output?.toString() as string);
+      await expect(fromSigner(wrappedContract, users[0].address).tx.mint(DEPOSITED_AMOUNT, users[0].address)).to.eventually.be
+        .fulfilled;
+      await expect(wrappedContract.query.balanceOf(users[0].address)).to.have.output(DEPOSITED_AMOUNT);
+      await expect(stableCoinContract.query.balanceOf(users[0].address)).to.have.output(MINTED_AMOUNT - assets);
+    });
+  });
+
+  describe('wrapper that is not tax free', async () => {
+    it('refuses deposit', async () => {
+      await expect(fromSigner(wrappedContract, users[0].address).tx.deposit(DEPOSITED_AMOUNT, users[0].address)).to.eventually.be
+        .rejected;
+      await expect(wrappedContract.query.totalSupply()).to.have.output(0);
+    });
+
+    it('refuses mint', async () => {
+      await expect(fromSigner(wrappedContract, users[0].address).tx.mint(DEPOSITED_AMOUNT, users[0].address)).to.eventually.be
+        .rejected;
+    });
+  });
+});
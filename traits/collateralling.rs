@@ -30,6 +30,7 @@ pub trait CollaterallingInternal {
         to: AccountId,
         amount: Balance,
     ) -> Result<(), PSP22Error>;
+    fn _receive_collateral(&mut self, amount: Balance);
 }
 
 /// Enum of errors raised by our lending smart contract
@@ -0,0 +1,104 @@
+use brush::{
+    contracts::{
+        ownable::OwnableError, pausable::PausableError, psp22::PSP22Error,
+        reentrancy_guard::ReentrancyGuardError,
+    },
+    traits::{AccountId, Balance},
+};
+use ink_prelude::{string::String, vec::Vec};
+
+#[brush::wrapper]
+pub type FlashLendingRef = dyn FlashLending;
+
+#[brush::trait_definition]
+pub trait FlashLending {
+    // lends amount of token to receiver for the duration of one call of receivers on_flash_loan
+    #[ink(message)]
+    fn flash_loan(
+        &mut self,
+        receiver: AccountId,
+        amount: Balance,
+        data: Vec<u8>,
+    ) -> Result<(), FlashLendingError>;
+    #[ink(message)]
+    fn set_max_flash_loan(&mut self, new_max_flash_loan: Balance) -> Result<(), FlashLendingError>;
+    #[ink(message)]
+    fn set_flash_fee_e6(&mut self, new_flash_fee_e6: u128) -> Result<(), FlashLendingError>;
+}
+
+#[brush::trait_definition]
+pub trait FlashLendingView {
+    #[ink(message)]
+    fn max_flash_loan(&self) -> Balance;
+    #[ink(message)]
+    fn flash_fee(&self, amount: Balance) -> Balance;
+    #[ink(message)]
+    fn flash_fee_e6(&self) -> u128;
+}
+
+#[brush::wrapper]
+pub type FlashBorrowerRef = dyn FlashBorrower;
+
+/// Must be implemented by receivers of flash loans. After the call lender takes back amount + fee.
+#[brush::trait_definition]
+pub trait FlashBorrower {
+    #[ink(message)]
+    fn on_flash_loan(
+        &mut self,
+        initiator: AccountId,
+        token: AccountId,
+        amount: Balance,
+        fee: Balance,
+        data: Vec<u8>,
+    ) -> Result<(), FlashBorrowerError>;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum FlashBorrowerError {
+    FlashloanRejected(String),
+}
+
+/// Enum of errors raised by our lending smart contract
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum FlashLendingError {
+    AmountAboveMaximum,
+    FeeAboveOne,
+    CallbackFailed,
+    FlashBorrowerError(FlashBorrowerError),
+    PSP22Error(PSP22Error),
+    OwnableError(OwnableError),
+    PausableError(PausableError),
+    ReentrancyGuardError(ReentrancyGuardError),
+}
+
+impl From<FlashBorrowerError> for FlashLendingError {
+    fn from(error: FlashBorrowerError) -> Self {
+        FlashLendingError::FlashBorrowerError(error)
+    }
+}
+
+impl From<PSP22Error> for FlashLendingError {
+    fn from(error: PSP22Error) -> Self {
+        FlashLendingError::PSP22Error(error)
+    }
+}
+
+impl From<OwnableError> for FlashLendingError {
+    fn from(error: OwnableError) -> Self {
+        FlashLendingError::OwnableError(error)
+    }
+}
+
+impl From<PausableError> for FlashLendingError {
+    fn from(error: PausableError) -> Self {
+        FlashLendingError::PausableError(error)
+    }
+}
+
+impl From<ReentrancyGuardError> for FlashLendingError {
+    fn from(error: ReentrancyGuardError) -> Self {
+        FlashLendingError::ReentrancyGuardError(error)
+    }
+}
@@ -0,0 +1,55 @@
+use brush::{
+    contracts::{access_control::AccessControlError, ownable::OwnableError, psp22::PSP22Error},
+    traits::{AccountId, Balance},
+};
+
+#[brush::wrapper]
+pub type FreezingRef = dyn Freezing + FreezingView;
+
+#[brush::wrapper]
+pub type FreezingViewRef = dyn FreezingView;
+
+/// Frozen accounts can't send, receive, be minted to or take new vault debt
+#[brush::trait_definition]
+pub trait Freezing {
+    #[ink(message)]
+    fn freeze(&mut self, account: AccountId) -> Result<(), FreezingError>;
+    #[ink(message)]
+    fn unfreeze(&mut self, account: AccountId) -> Result<(), FreezingError>;
+    // burns whole balance of frozen account, returns burned amount
+    #[ink(message)]
+    fn wipe_frozen(&mut self, account: AccountId) -> Result<Balance, FreezingError>;
+}
+
+#[brush::trait_definition]
+pub trait FreezingView {
+    #[ink(message)]
+    fn is_frozen(&self, account: AccountId) -> bool;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum FreezingError {
+    NotFrozen,
+    AccessControlError(AccessControlError),
+    OwnableError(OwnableError),
+    PSP22Error(PSP22Error),
+}
+
+impl From<AccessControlError> for FreezingError {
+    fn from(error: AccessControlError) -> Self {
+        FreezingError::AccessControlError(error)
+    }
+}
+
+impl From<OwnableError> for FreezingError {
+    fn from(error: OwnableError) -> Self {
+        FreezingError::OwnableError(error)
+    }
+}
+
+impl From<PSP22Error> for FreezingError {
+    fn from(error: PSP22Error) -> Self {
+        FreezingError::PSP22Error(error)
+    }
+}
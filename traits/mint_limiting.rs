@@ -0,0 +1,81 @@
+use brush::{
+    contracts::traits::{ownable::*, psp22::PSP22Error},
+    traits::{AccountId, Balance, Timestamp},
+};
+use ink_prelude::string::String;
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+
+#[cfg(feature = "std")]
+use ink_storage::traits::StorageLayout;
+
+#[brush::wrapper]
+pub type MintLimitingRef = dyn MintLimiting + MintLimitingView;
+
+/// Limits of minting per minter. Minters without quota are not limited.
+#[brush::trait_definition]
+pub trait MintLimiting {
+    #[ink(message)]
+    fn set_minter_quota(
+        &mut self,
+        minter: AccountId,
+        quota: Option<MinterQuota>,
+    ) -> Result<(), MintLimitError>;
+}
+
+#[brush::trait_definition]
+pub trait MintLimitingView {
+    #[ink(message)]
+    fn minter_quota(&self, minter: AccountId) -> Option<MinterQuota>;
+
+    // minted by minter minus burned by minter
+    #[ink(message)]
+    fn minter_outstanding(&self, minter: AccountId) -> Balance;
+
+    // how much minter can mint now, Balance::MAX if minter has no quota
+    #[ink(message)]
+    fn remaining_mint_capacity(&self, minter: AccountId) -> Balance;
+}
+
+/// max_outstanding caps minted minus burned amount of the minter.
+/// At most window_limit can be minted within window, used capacity is released linearly over window. window == 0 turns the window limit off.
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct MinterQuota {
+    pub max_outstanding: Balance,
+    pub window_limit: Balance,
+    pub window: Timestamp,
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum MintLimitError {
+    QuotaExceeded,
+    OwnableError(OwnableError),
+}
+
+impl From<OwnableError> for MintLimitError {
+    fn from(error: OwnableError) -> Self {
+        MintLimitError::OwnableError(error)
+    }
+}
+
+// mint of PSP22Mintable can only fail with PSP22Error
+impl From<MintLimitError> for PSP22Error {
+    fn from(error: MintLimitError) -> Self {
+        match error {
+            MintLimitError::QuotaExceeded => PSP22Error::Custom(String::from("QuotaExceeded")),
+            MintLimitError::OwnableError(_) => PSP22Error::Custom(String::from("OwnableError")),
+        }
+    }
+}
@@ -1,12 +1,23 @@
 pub mod collateralling;
 pub mod emitting;
+pub mod flash_lending;
+pub mod freezing;
 pub mod managing;
 pub mod measuring;
+pub mod mint_limiting;
 pub mod oracling;
 pub mod pausing;
+pub mod psp22_batch;
+pub mod psp22_permit;
 pub mod psp22_rated;
+pub mod reporting;
+pub mod savings;
 pub mod shares_profit_controlling;
 pub mod shares_profit_generating;
+pub mod snapshotting;
 pub mod stable_controlling;
+pub mod swap_adapter;
+pub mod upgrading;
 pub mod vault;
 pub mod vault_controlling;
+pub mod wrapping;
@@ -0,0 +1,19 @@
+use brush::{
+    contracts::psp22::PSP22Error,
+    traits::{AccountId, Balance},
+};
+use ink_prelude::vec::Vec;
+
+#[brush::wrapper]
+pub type PSP22BatchRef = dyn PSP22Batch;
+
+/// Transfers from caller to many recipients in one call. Fails without any change if the caller can't cover the sum.
+#[brush::trait_definition]
+pub trait PSP22Batch {
+    #[ink(message)]
+    fn transfer_batch(
+        &mut self,
+        transfers: Vec<(AccountId, Balance)>,
+        data: Vec<u8>,
+    ) -> Result<(), PSP22Error>;
+}
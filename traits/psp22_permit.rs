@@ -0,0 +1,54 @@
+use brush::{
+    contracts::psp22::PSP22Error,
+    traits::{AccountId, Balance, Timestamp},
+};
+
+#[brush::wrapper]
+pub type PSP22PermitRef = dyn PSP22Permit;
+
+/// Approvals signed by the owner off-chain and submitted by anyone.
+/// Signature is ECDSA over blake2x256 of SCALE encoded (domain_separator, owner, spender, value, nonce, deadline),
+/// owner is blake2x256 of the compressed public key of the signer.
+/// So only ECDSA accounts can sign permits, sr25519 and ed25519 accounts have to use approve.
+/// Domain separator binds the permit to the contract and to chain_id set by the owner.
+#[brush::trait_definition]
+pub trait PSP22Permit {
+    #[ink(message)]
+    fn permit(
+        &mut self,
+        owner: AccountId,
+        spender: AccountId,
+        value: Balance,
+        deadline: Timestamp,
+        signature: [u8; 65],
+    ) -> Result<(), PermitError>;
+
+    // nonce the next permit of owner has to be signed with
+    #[ink(message)]
+    fn nonces(&self, owner: AccountId) -> u64;
+
+    #[ink(message)]
+    fn domain_separator(&self) -> [u8; 32];
+
+    // genesis hash of the chain, permits are refused until it is set
+    #[ink(message)]
+    fn set_chain_id(&mut self, chain_id: [u8; 32]) -> Result<(), PSP22Error>;
+
+    #[ink(message)]
+    fn chain_id(&self) -> Option<[u8; 32]>;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum PermitError {
+    Expired,
+    InvalidSignature,
+    PSP22Error(PSP22Error),
+    ChainIdNotSet,
+}
+
+impl From<PSP22Error> for PermitError {
+    fn from(error: PSP22Error) -> Self {
+        PermitError::PSP22Error(error)
+    }
+}
@@ -1,7 +1,12 @@
 use brush::{
-    contracts::traits::psp22::*,
+    contracts::traits::{access_control::RoleType, psp22::*},
     traits::{AccountId, Balance, Timestamp},
 };
+use ink_prelude::vec::Vec;
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+
+#[cfg(feature = "std")]
+use ink_storage::traits::StorageLayout;
 
 #[brush::wrapper]
 pub type PSP22RatedRef = dyn PSP22Rated + PSP22 + PSP22RatedView;
@@ -17,6 +22,9 @@ pub trait PSP22Rated {
     #[ink(message)]
     fn set_is_tax_free(&mut self, account: AccountId, set_to: bool) -> Result<(), PSP22Error>;
 
+    #[ink(message)]
+    fn set_tax_policy(&mut self, tax_policy: TaxPolicy) -> Result<(), PSP22Error>;
+
     #[ink(message)]
     fn set_stable_controller_address(
         &mut self,
@@ -42,6 +50,9 @@ pub trait PSP22RatedView {
     #[ink(message)]
     fn rated_supply(&self) -> Balance;
 
+    #[ink(message)]
+    fn scaled_rated_supply(&self) -> Balance;
+
     #[ink(message)]
     fn unrated_supply(&self) -> Balance;
 
@@ -52,7 +63,7 @@ pub trait PSP22RatedView {
     fn last_current_denominator_update_timestamp(&self) -> Timestamp;
 
     #[ink(message)]
-    fn applied_denominator_e12(&self, account: AccountId) -> Balance;
+    fn scaled_balance_of(&self, account: AccountId) -> Balance;
 
     #[ink(message)]
     fn is_unrated(&self, account: AccountId) -> bool;
@@ -69,17 +80,20 @@ pub trait PSP22RatedView {
     #[ink(message)]
     fn is_tax_free(&self, account: AccountId) -> bool;
 
+    #[ink(message)]
+    fn tax_policy(&self) -> TaxPolicy;
+
     #[ink(message)]
     fn account_debt(&self, account: AccountId) -> Balance;
 }
 
 #[brush::trait_definition]
 pub trait PSP22RatedInternals {
-    fn _unupdated_balance_of(&self, account: &AccountId) -> Balance;
+    fn _raw_balance_of(&self, account: &AccountId) -> Balance;
     fn _is_unrated(&self, account: &AccountId) -> bool;
-    fn _applied_denominator_e12(&self, account: &AccountId) -> u128;
     fn _is_tax_free(&self, account: &AccountId) -> bool;
     fn _account_debt(&self, account: &AccountId) -> Balance;
+    fn _get_current_denominator_e12(&self) -> u128;
     fn _update_current_denominator_e12(&mut self) -> u128;
     fn _switch_is_unrated(&mut self, account: AccountId) -> Result<(), PSP22Error>;
     fn _increase_balance(
@@ -96,3 +110,46 @@ pub trait PSP22RatedInternals {
     ) -> Result<(), PSP22Error>;
     fn _calculate_tax(&self, account: AccountId, amount: Balance, tax_e6: u128) -> Balance;
 }
+
+/// Whose tax status decides about the tax. With Both, sender and recipient are taxed separately.
+/// Tax is always taken from the transfered amount.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub enum TaxedParty {
+    Sender,
+    Recipient,
+    Both,
+}
+
+impl Default for TaxedParty {
+    fn default() -> Self {
+        TaxedParty::Recipient
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub enum TaxDestination {
+    // generated profit, collected by shares profit controller (treassury and owner)
+    Profit,
+    // credited to account, e.g. stability pool
+    Account(AccountId),
+    // removed from supply
+    Burn,
+}
+
+/// Rules of transfer tax of PSP22Rated.
+/// Accounts with any of exempt_roles are tax free. Tax is split across destinations by weights, no destinations means everything goes to Profit.
+#[derive(
+    Default, Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct TaxPolicy {
+    pub taxed_party: TaxedParty,
+    pub exempt_roles: Vec<RoleType>,
+    pub destinations: Vec<(TaxDestination, u32)>,
+}
@@ -0,0 +1,78 @@
+use brush::{
+    contracts::traits::ownable::*,
+    traits::{AccountId, Balance},
+};
+use ink_prelude::vec::Vec;
+
+#[brush::wrapper]
+pub type ReportingRef = dyn Reporting + ReportingView;
+
+/// Read-only aggregation of registered vaults, stable coin and shares profit controller for dashboards.
+#[brush::trait_definition]
+pub trait Reporting {
+    #[ink(message)]
+    fn add_vault(&mut self, vault: AccountId) -> Result<(), ReportingError>;
+
+    #[ink(message)]
+    fn remove_vault(&mut self, vault: AccountId) -> Result<(), ReportingError>;
+}
+
+#[brush::trait_definition]
+pub trait ReportingView {
+    #[ink(message)]
+    fn get_vaults(&self) -> Vec<AccountId>;
+
+    #[ink(message)]
+    fn get_stable_coin_address(&self) -> AccountId;
+
+    #[ink(message)]
+    fn get_shares_profit_controller_address(&self) -> AccountId;
+
+    // account debt is not enumerable on stable coin, so debt backed supply is summed over debt_holders only
+    #[ink(message)]
+    fn get_system_report(&self, debt_holders: Vec<AccountId>) -> SystemReport;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct VaultReport {
+    pub vault: AccountId,
+    pub total_debt: Balance,
+    pub collateral_amount: Balance,
+    pub collateral_price_e6: u128,
+    pub collateral_value: Balance, // in stable coin units
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct SystemReport {
+    pub vaults: Vec<VaultReport>,
+    pub total_debt: Balance,
+    pub collateral_value: Balance,
+    // collateral_value / total_debt, u128::MAX without debt
+    pub collateral_ratio_e6: u128,
+    // generated by vaults and stable coin but not collected + collected by shares profit controller but not distributed
+    pub undistributed_profit: i128,
+    pub treassury_holdings: Balance,
+    pub rated_supply: Balance,
+    pub unrated_supply: Balance,
+    pub total_supply: Balance,
+    // part of debt_holders balances covered by their account_debt (untaxed part of their transfers)
+    // partial: only accounts passed in debt_holders are counted, debt of other accounts is missing
+    // so it is a lower bound that depends on how complete the passed list is
+    pub debt_backed_supply: Balance,
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum ReportingError {
+    VaultAlreadyAdded,
+    VaultNotAdded,
+    OwnableError(OwnableError),
+}
+
+impl From<OwnableError> for ReportingError {
+    fn from(error: OwnableError) -> Self {
+        ReportingError::OwnableError(error)
+    }
+}
@@ -0,0 +1,98 @@
+use brush::{
+    contracts::traits::{ownable::*, psp22::PSP22Error},
+    traits::{AccountId, Balance, Timestamp},
+};
+
+#[brush::wrapper]
+pub type SavingsRef = dyn Savings + SavingsView;
+
+/// Opt-in savings for stable coin holders.
+/// Deposits grow with savings_rate_e12 set by stable controller, the interest is funded from profit by shares profit controller.
+#[brush::trait_definition]
+pub trait Savings {
+    // transfers amount of stable coin from caller to savings
+    #[ink(message)]
+    fn deposit(&mut self, amount: Balance) -> Result<(), SavingsError>;
+
+    // transfers amount of stable coin from savings to caller
+    #[ink(message)]
+    fn withdraw(&mut self, amount: Balance) -> Result<(), SavingsError>;
+
+    // accrues interest up to now, returns interest accrued by this call
+    #[ink(message)]
+    fn drip(&mut self) -> Result<Balance, SavingsError>;
+
+    // only stable controller
+    #[ink(message)]
+    fn set_savings_rate_e12(&mut self, new_savings_rate_e12: u128) -> Result<(), SavingsError>;
+
+    // only shares profit controller, amount of stable coin has to be minted to savings before the call
+    #[ink(message)]
+    fn fund(&mut self, amount: Balance) -> Result<(), SavingsError>;
+
+    #[ink(message)]
+    fn set_stable_controller_address(
+        &mut self,
+        new_stable_controller_address: AccountId,
+    ) -> Result<(), SavingsError>;
+
+    #[ink(message)]
+    fn set_shares_profit_controller_address(
+        &mut self,
+        new_shares_profit_controller_address: AccountId,
+    ) -> Result<(), SavingsError>;
+}
+
+#[brush::trait_definition]
+pub trait SavingsView {
+    #[ink(message)]
+    fn savings_balance_of(&self, account: AccountId) -> Balance;
+
+    #[ink(message)]
+    fn total_savings(&self) -> Balance;
+
+    #[ink(message)]
+    fn get_savings_rate_e12(&self) -> u128;
+
+    #[ink(message)]
+    fn get_chi_e12(&self) -> u128;
+
+    #[ink(message)]
+    fn get_last_drip_timestamp(&self) -> Timestamp;
+
+    // interest accrued up to now that was not funded yet
+    #[ink(message)]
+    fn get_unfunded_interest(&self) -> Balance;
+
+    #[ink(message)]
+    fn get_stable_coin_address(&self) -> AccountId;
+
+    #[ink(message)]
+    fn get_stable_controller_address(&self) -> AccountId;
+
+    #[ink(message)]
+    fn get_shares_profit_controller_address(&self) -> AccountId;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum SavingsError {
+    InsufficientSavings,
+    NotFunded,
+    StableController,
+    SharesProfitController,
+    OwnableError(OwnableError),
+    PSP22Error(PSP22Error),
+}
+
+impl From<OwnableError> for SavingsError {
+    fn from(error: OwnableError) -> Self {
+        SavingsError::OwnableError(error)
+    }
+}
+
+impl From<PSP22Error> for SavingsError {
+    fn from(error: PSP22Error) -> Self {
+        SavingsError::PSP22Error(error)
+    }
+}
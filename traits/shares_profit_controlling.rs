@@ -3,6 +3,7 @@ use brush::{
     traits::AccountId,
 };
 
+use super::savings::SavingsError;
 use super::shares_profit_generating::SPGeneratingError;
 
 /// Combination of all traits of the contract to simplify calls to the contract
@@ -37,6 +38,13 @@ pub trait SPControlling {
         new_treassury_part_e6: u128,
     ) -> Result<(), SPControllingError>;
 
+    // savings interest is funded from income before treassury and owner parts
+    #[ink(message)]
+    fn set_savings_address(
+        &mut self,
+        new_savings_address: AccountId,
+    ) -> Result<(), SPControllingError>;
+
     // shares
 
     #[ink(message)]
@@ -63,9 +71,16 @@ pub trait SPControllingView {
 
     #[ink(message)]
     fn get_treassury_part_e6(&self) -> u128;
+
+    #[ink(message)]
+    fn get_savings_address(&self) -> AccountId;
 }
 
-pub trait SPControllingInternal {}
+pub trait SPControllingInternal {
+    // savings address is stored by the contract after its upgrading data, so it is added without shifting the layout
+    fn _savings_address(&self) -> AccountId;
+    fn _set_savings_address(&mut self, savings_address: AccountId);
+}
 
 /// Enum of errors raised by our lending smart contract
 #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
@@ -77,6 +92,7 @@ pub enum SPControllingError {
     PSP22Error(PSP22Error),
     OwnableError(OwnableError),
     SPGeneratingError(SPGeneratingError),
+    SavingsError(SavingsError),
 }
 
 impl From<PSP22Error> for SPControllingError {
@@ -96,3 +112,9 @@ impl From<SPGeneratingError> for SPControllingError {
         SPControllingError::SPGeneratingError(error)
     }
 }
+
+impl From<SavingsError> for SPControllingError {
+    fn from(error: SavingsError) -> Self {
+        SPControllingError::SavingsError(error)
+    }
+}
@@ -0,0 +1,61 @@
+use brush::{
+    contracts::traits::ownable::*,
+    traits::{AccountId, Balance},
+};
+
+#[brush::wrapper]
+pub type SnapshottingRef = dyn Snapshotting;
+
+/// Balances and total supply are checkpointed lazily, on the first change after a snapshot
+#[brush::trait_definition]
+pub trait Snapshotting {
+    // takes a snapshot of balances and total supply, returns its id
+    #[ink(message)]
+    fn snapshot(&mut self) -> Result<u32, SnapshottingError>;
+
+    #[ink(message)]
+    fn balance_of_at(
+        &self,
+        account: AccountId,
+        snapshot_id: u32,
+    ) -> Result<Balance, SnapshottingError>;
+
+    #[ink(message)]
+    fn total_supply_at(&self, snapshot_id: u32) -> Result<Balance, SnapshottingError>;
+
+    // id of the last snapshot, 0 if none was taken
+    #[ink(message)]
+    fn current_snapshot_id(&self) -> u32;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum SnapshottingError {
+    NonexistentSnapshot,
+    OwnableError(OwnableError),
+}
+
+impl From<OwnableError> for SnapshottingError {
+    fn from(error: OwnableError) -> Self {
+        SnapshottingError::OwnableError(error)
+    }
+}
+
+// returns index of the first checkpoint taken for snapshot_id or later one, checkpoint ids are increasing
+pub fn find_checkpoint<F: Fn(u32) -> u32>(count: u32, snapshot_id: u32, id_at: F) -> Option<u32> {
+    let mut low = 0;
+    let mut high = count;
+    while low < high {
+        let middle = (low + high) / 2;
+        if id_at(middle) < snapshot_id {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    if low == count {
+        None
+    } else {
+        Some(low)
+    }
+}
@@ -3,6 +3,7 @@ use brush::contracts::traits::psp22::*;
 use brush::traits::AccountId;
 
 use super::measuring::*;
+use super::savings::SavingsError;
 
 #[brush::wrapper]
 pub type SControllingContractRef = dyn SControlling + SControllingView + Ownable;
@@ -20,6 +21,13 @@ pub trait SControlling {
         &mut self,
         new_measurer_address: AccountId,
     ) -> Result<(), SControllingError>;
+
+    // if set, positive rates for holders are paid only by savings
+    #[ink(message)]
+    fn set_savings_address(
+        &mut self,
+        new_savings_address: AccountId,
+    ) -> Result<(), SControllingError>;
 }
 
 #[brush::trait_definition]
@@ -29,11 +37,17 @@ pub trait SControllingView {
 
     #[ink(message)]
     fn get_measurer_address(&mut self) -> AccountId;
+
+    #[ink(message)]
+    fn get_savings_address(&mut self) -> AccountId;
 }
 
 pub trait SControllingInternal {
     fn _stability_measure_parameter_to_interest_rate(&self, state_parameter: u8) -> i128;
     fn _ausd_usd_price_e6_to_tax_e6(&self, ausd_usd_price_e6: u128) -> u128;
+    // savings address is stored by the contract after its upgrading data, so it is added without shifting the layout
+    fn _savings_address(&self) -> AccountId;
+    fn _set_savings_address(&mut self, savings_address: AccountId);
 }
 
 /// Enum of errors raised by our lending smart contract
@@ -44,6 +58,7 @@ pub enum SControllingError {
     OwnableError(OwnableError),
     MeasuringError(MeasuringError),
     PSP22Error(PSP22Error),
+    SavingsError(SavingsError),
 }
 
 impl From<OwnableError> for SControllingError {
@@ -63,3 +78,9 @@ impl From<PSP22Error> for SControllingError {
         SControllingError::PSP22Error(error)
     }
 }
+
+impl From<SavingsError> for SControllingError {
+    fn from(error: SavingsError) -> Self {
+        SControllingError::SavingsError(error)
+    }
+}
@@ -0,0 +1,46 @@
+use brush::{
+    contracts::psp22::PSP22Error,
+    traits::{AccountId, Balance},
+};
+
+#[brush::wrapper]
+pub type SwapAdapterRef = dyn SwapAdapter;
+
+/// Swaps tokens for contracts that lever or delever their positions.
+/// amount_in of token_in has to be transfered to the adapter before swap is called.
+#[brush::trait_definition]
+pub trait SwapAdapter {
+    // swaps amount_in of token_in already held by the adapter and sends the output to to, returns output amount
+    #[ink(message)]
+    fn swap(
+        &mut self,
+        token_in: AccountId,
+        token_out: AccountId,
+        amount_in: Balance,
+        min_amount_out: Balance,
+        to: AccountId,
+    ) -> Result<Balance, SwapAdapterError>;
+    #[ink(message)]
+    fn get_amount_out(
+        &self,
+        token_in: AccountId,
+        token_out: AccountId,
+        amount_in: Balance,
+    ) -> Result<Balance, SwapAdapterError>;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum SwapAdapterError {
+    UnsupportedPair,
+    InsufficientInputAmount,
+    InsufficientOutputAmount,
+    InsufficientLiquidity,
+    PSP22Error(PSP22Error),
+}
+
+impl From<PSP22Error> for SwapAdapterError {
+    fn from(error: PSP22Error) -> Self {
+        SwapAdapterError::PSP22Error(error)
+    }
+}
@@ -0,0 +1,50 @@
+use brush::contracts::traits::ownable::*;
+
+#[brush::wrapper]
+pub type UpgradingRef = dyn Upgrading;
+
+/// Owner replaces code of the contract with upgrade.
+/// State changing messages of the contract are guarded by when_migrated, so the first of them after an upgrade moves storage to the layout expected by the new code.
+/// Views can't migrate, owner can call migrate right after upgrade so views don't read unmigrated storage.
+#[brush::trait_definition]
+pub trait Upgrading {
+    #[ink(message)]
+    fn upgrade(&mut self, code_hash: [u8; 32]) -> Result<(), UpgradingError>;
+
+    // runs pending migrations explicitly, anyone can call it
+    #[ink(message)]
+    fn migrate(&mut self) -> Result<(), UpgradingError>;
+
+    #[ink(message)]
+    fn get_storage_version(&self) -> u32;
+
+    #[ink(message)]
+    fn get_code_hash(&self) -> Option<[u8; 32]>;
+}
+
+pub trait UpgradingInternal {
+    // version of storage layout the code works with
+    fn _code_storage_version(&self) -> u32;
+    // migration hook, moves storage from from_version to _code_storage_version
+    fn _migrate(&mut self, from_version: u32) -> Result<(), UpgradingError>;
+    // runs _migrate if storage_version is lower than _code_storage_version
+    fn _ensure_migrated(&mut self) -> Result<(), UpgradingError>;
+    fn _emit_upgraded_event(&self, _old_code_hash: Option<[u8; 32]>, _new_code_hash: [u8; 32]);
+    fn _emit_migrated_event(&self, _from_version: u32, _to_version: u32);
+}
+
+/// Enum of errors raised by upgrading
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum UpgradingError {
+    CodeHashNotSet,
+    StorageVersionAboveCode,
+    MigrationFailed,
+    OwnableError(OwnableError),
+}
+
+impl From<OwnableError> for UpgradingError {
+    fn from(error: OwnableError) -> Self {
+        UpgradingError::OwnableError(error)
+    }
+}
@@ -3,12 +3,15 @@ use brush::{
         ownable::OwnableError, pausable::PausableError, psp22::PSP22Error, psp34::PSP34Error,
         traits::ownable::*, traits::pausable::*,
     },
-    traits::{AccountId, Balance},
+    traits::{AccountId, Balance, Timestamp},
 };
 
+use ink_prelude::vec::Vec;
+
 use crate::traits::collateralling::*;
 use crate::traits::emitting::*;
 use crate::traits::shares_profit_generating::*;
+use crate::traits::swap_adapter::*;
 
 /// Combination of all traits of the contract to simplify calls to the contract
 #[brush::wrapper]
@@ -38,9 +41,65 @@ pub trait Vault {
     #[ink(message)]
     fn borrow_token(&mut self, vault_id: u128, amount: Balance) -> Result<(), VaultError>;
     #[ink(message)]
-    fn pay_back_token(&mut self, vault_id: u128, amount: Balance) -> Result<(), VaultError>;
+    fn repay(&mut self, vault_id: u128, amount: Balance) -> Result<(), VaultError>;
+    #[ink(message)]
+    fn open_and_borrow(
+        &mut self,
+        collateral: Balance,
+        debt: Balance,
+    ) -> Result<(u128, Balance, Balance), VaultError>;
+    #[ink(message)]
+    fn adjust_vault(
+        &mut self,
+        vault_id: u128,
+        collateral_delta: i128,
+        debt_delta: i128,
+    ) -> Result<(Balance, Balance), VaultError>;
+    #[ink(message)]
+    fn repay_all_and_close(&mut self, vault_id: u128) -> Result<(Balance, Balance), VaultError>;
+    #[ink(message)]
+    fn merge_vaults(&mut self, into_id: u128, from_id: u128) -> Result<(), VaultError>;
+    #[ink(message)]
+    fn split_vault(
+        &mut self,
+        vault_id: u128,
+        collateral: Balance,
+        debt: Balance,
+    ) -> Result<u128, VaultError>;
+    #[ink(message)]
+    fn leverage(
+        &mut self,
+        vault_id: u128,
+        target_ratio_e6: u128,
+        min_collateral_out: Balance,
+    ) -> Result<(Balance, Balance), VaultError>;
+    #[ink(message)]
+    fn deleverage(
+        &mut self,
+        vault_id: u128,
+        target_ratio_e6: u128,
+        min_stable_out: Balance,
+    ) -> Result<(Balance, Balance), VaultError>;
     #[ink(message)]
     fn buy_risky_vault(&mut self, vault_id: u128) -> Result<(), VaultError>;
+    /// Sends collateral collected as flash loan fees to treassury of shares profit controller.
+    #[ink(message)]
+    fn collect_flash_fees(&mut self) -> Result<Balance, VaultError>;
+    /// Approval for one vault (Some(vault_id)) ends with the next transfer of the vault, approval for all vaults (None) does not.
+    #[ink(message)]
+    fn approve_operator(
+        &mut self,
+        operator: AccountId,
+        vault_id: Option<u128>,
+        approved: bool,
+    ) -> Result<(), VaultError>;
+    #[ink(message)]
+    fn set_recipient(&mut self, recipient: AccountId) -> Result<(), VaultError>;
+    /// Opt-in for receiving vaults with debt, false by default for every account.
+    /// Transfer of a vault with debt moves the debt to the recipient, so without the opt-in it fails with DebtNotAccepted.
+    /// Vaults without debt can be transfered to any account.
+    #[ink(message)]
+    fn set_debt_vaults_accepted(&mut self, accepted: bool) -> Result<(), VaultError>;
     #[ink(message)]
     fn be_controlled(
         &mut self,
@@ -56,10 +115,26 @@ pub trait Vault {
     #[ink(message)]
     fn set_oracle_address(&mut self, new_oracle_address: AccountId) -> Result<(), VaultError>;
     #[ink(message)]
+    fn set_swap_adapter_address(
+        &mut self,
+        new_swap_adapter_address: AccountId,
+    ) -> Result<(), VaultError>;
+    #[ink(message)]
     fn set_liquidator_address(
         &mut self,
         new_liquidator_address: AccountId,
     ) -> Result<(), VaultError>;
+    #[ink(message)]
+    fn set_interest_rate_premium_e12(
+        &mut self,
+        new_interest_rate_premium_e12: i128,
+    ) -> Result<(), VaultError>;
+    #[ink(message)]
+    fn set_min_vault_debt(&mut self, new_min_vault_debt: Balance) -> Result<(), VaultError>;
+    #[ink(message)]
+    fn set_max_total_debt(&mut self, new_max_total_debt: Balance) -> Result<(), VaultError>;
+    #[ink(message)]
+    fn set_max_emited_supply(&mut self, new_max_emited_supply: Balance) -> Result<(), VaultError>;
 }
 
 #[brush::trait_definition]
@@ -78,23 +153,158 @@ pub trait VaultView {
     fn get_debt_ceiling(&self, vault_id: u128) -> Balance;
     #[ink(message)]
     fn get_liquidator_address(&self) -> AccountId;
+    #[ink(message)]
+    fn get_swap_adapter_address(&self) -> AccountId;
+    #[ink(message)]
+    fn get_interest_rate_premium_e12(&self) -> i128;
+    #[ink(message)]
+    fn get_current_interest_rate_e12(&self) -> i128;
+    #[ink(message)]
+    fn get_vault_debt_at(&self, vault_id: u128, timestamp: Timestamp) -> Balance;
+    #[ink(message)]
+    fn is_operator(&self, owner: AccountId, operator: AccountId, vault_id: Option<u128>) -> bool;
+    #[ink(message)]
+    fn get_recipient(&self, owner: AccountId) -> AccountId;
+    #[ink(message)]
+    fn accepts_debt_vaults(&self, account: AccountId) -> bool;
+    #[ink(message)]
+    fn vaults_of(&self, owner: AccountId, start: u128, limit: u128) -> Vec<u128>;
+    #[ink(message)]
+    fn vault_info(&self, vault_id: u128) -> Option<VaultInfo>;
+    #[ink(message)]
+    fn get_health_factor_e6(&self, vault_id: u128) -> u128;
+    #[ink(message)]
+    fn get_liquidation_price_e6(&self, vault_id: u128) -> u128;
+    #[ink(message)]
+    fn get_max_borrowable(&self, vault_id: u128) -> Balance;
+    #[ink(message)]
+    fn get_max_withdrawable(&self, vault_id: u128) -> Balance;
+    #[ink(message)]
+    fn risky_vaults(&self, start: u128, limit: u128) -> Vec<u128>;
+    #[ink(message)]
+    fn get_min_vault_debt(&self) -> Balance;
+    #[ink(message)]
+    fn get_max_total_debt(&self) -> Balance;
+    #[ink(message)]
+    fn get_max_emited_supply(&self) -> Balance;
+    #[ink(message)]
+    fn get_flash_fee_collateral(&self) -> Balance;
 }
 pub trait VaultInternal {
     fn _emit_deposit_event(&self, _vault_id: u128, _current_collateral: Balance);
     fn _emit_withdraw_event(&self, _vault_id: u128, _current_collateral: Balance);
     fn _emit_borrow_event(&self, _vault_id: u128, _borrowed: Balance);
-    fn _emit_pay_back_event(&self, _vault_id: u128, _pay_backed: Balance);
+    fn _emit_pay_back_event(&self, _vault_id: u128, _payer: AccountId, _pay_backed: Balance);
+    fn _emit_operator_approval_event(
+        &self,
+        _owner: AccountId,
+        _operator: AccountId,
+        _vault_id: Option<u128>,
+        _approved: bool,
+    );
+    fn _emit_recipient_set_event(&self, _owner: AccountId, _recipient: AccountId);
+    fn _emit_vaults_merged_event(&self, _into_id: u128, _from_id: u128);
+    fn _emit_vault_split_event(
+        &self,
+        _vault_id: u128,
+        _new_vault_id: u128,
+        _collateral: Balance,
+        _debt: Balance,
+    );
+    fn _emit_debt_vaults_accepted_event(&self, _account: AccountId, _accepted: bool);
+    fn _create_vault(&mut self, owner: AccountId) -> Result<u128, VaultError>;
+    fn _destroy_vault(&mut self, vault_id: u128, vault_owner: AccountId) -> Result<(), VaultError>;
+    fn _deposit_collateral(
+        &mut self,
+        vault_id: u128,
+        from: AccountId,
+        amount: Balance,
+    ) -> Result<(), VaultError>;
+    fn _credit_collateral(&mut self, vault_id: u128, amount: Balance);
+    fn _withdraw_collateral(
+        &mut self,
+        vault_id: u128,
+        to: AccountId,
+        amount: Balance,
+    ) -> Result<(), VaultError>;
+    fn _borrow_token(
+        &mut self,
+        vault_id: u128,
+        vault_owner: AccountId,
+        amount: Balance,
+        to: AccountId,
+    ) -> Result<(), VaultError>;
+    fn _repay(
+        &mut self,
+        vault_id: u128,
+        vault_owner: AccountId,
+        payer: AccountId,
+        amount: Balance,
+    ) -> Result<Balance, VaultError>;
+    fn _move_vault_debt(
+        &mut self,
+        vault_id: u128,
+        from: AccountId,
+        to: AccountId,
+    ) -> Result<(), VaultError>;
+    fn _vault_attribute(&self, vault_id: u128, key: &[u8]) -> Option<Vec<u8>>;
+    fn _set_vault_attribute(&mut self, vault_id: u128, key: &str, value: Vec<u8>);
+    fn _ratio_bucket(&self, health_factor_e6: u128) -> &'static str;
+    fn _swap(
+        &mut self,
+        token_in: AccountId,
+        token_out: AccountId,
+        amount_in: Balance,
+        min_amount_out: Balance,
+    ) -> Result<Balance, VaultError>;
+    fn _check_collateralization(&mut self, vault_id: u128) -> Result<(), VaultError>;
+    fn _check_vault_operator(&self, vault_id: u128) -> Result<AccountId, VaultError>;
+    fn _is_operator(&self, owner: AccountId, operator: AccountId, vault_id: Option<u128>) -> bool;
+    fn _get_recipient(&self, owner: AccountId) -> AccountId;
+    fn _add_vault_to_owner(&mut self, owner: AccountId, vault_id: u128);
+    fn _remove_vault_from_owner(&mut self, owner: AccountId, vault_id: u128);
+    fn _collateral_ratio_e6(&self, collateral_value_e6: u128, debt: Balance) -> u128;
+    fn _health_factor_e6(&self, debt_ceiling: Balance, debt: Balance) -> u128;
+    fn _liquidation_price_e6(&self, collateral: Balance, debt: Balance) -> u128;
+    fn _max_borrowable(&self, debt_ceiling: Balance, debt: Balance) -> Balance;
+    fn _max_withdrawable(&self, collateral: Balance, debt: Balance) -> Balance;
     fn _get_debt_ceiling(&self, vault_id: u128) -> Balance;
     fn _collateral_value_e6(&self, collateral: Balance) -> u128;
     fn _vault_collateral_value_e6(&self, value_id: u128) -> u128;
     fn _update_vault_debt(&mut self, vault_id: u128) -> Result<Balance, VaultError>;
     fn _update_current_interest_coefficient_e12(&mut self) -> u128;
     fn _get_current_interest_coefficient_e12(&self) -> u128;
+    fn _get_interest_coefficient_at_e12(&self, timestamp: Timestamp) -> u128;
+    fn _get_interest_rate_e12(&self) -> i128;
+    fn _get_vault_debt_at(&self, vault_id: u128, timestamp: Timestamp) -> Balance;
+    fn _check_debt_limits(&self, debt_after: Balance, borrowed: Balance) -> Result<(), VaultError>;
     fn _get_debt_by_id(&self, vault_id: &u128) -> Balance;
     fn _get_collateral_by_id(&self, vault_id: &u128) -> Balance;
     fn _get_last_interest_coefficient_by_id_e12(&self, vault_id: &u128) -> Balance;
 }
 
+/// Summary of a vault position, debt includes accrued interest
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct VaultInfo {
+    pub owner: AccountId,
+    pub collateral: Balance,
+    pub debt: Balance,
+    pub collateral_value_e6: u128,
+    pub collateral_ratio_e6: u128,
+    pub debt_ceiling: Balance,
+    pub liquidation_price_e6: u128,
+    pub health_factor_e6: u128,
+}
+
+/// SCALE encoded in `data` of collateral transfer to the vault to choose the credited vault
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum DepositTarget {
+    Vault(u128),
+    NewVault,
+}
+
 #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub enum VaultError {
@@ -107,13 +317,27 @@ pub enum VaultError {
     VaultOwnership,
     CollateralBelowMinimum,
     CollateralAboveMinimum,
+    DebtBelowMinimum,
+    TotalDebtAboveMaximum,
+    EmitedSupplyAboveMaximum,
     Liquidator,
+    DebtNotAccepted,
+    SameVault,
+    AccountFrozen,
     PSP22Error(PSP22Error),
     PSP34Error(PSP34Error),
     PausableError(PausableError),
     CollaterallingError(CollaterallingError),
     OwnableError(OwnableError),
     EmittingError(EmittingError),
+    SwapFailed,
+    SwapAdapterError(SwapAdapterError),
+}
+
+impl From<SwapAdapterError> for VaultError {
+    fn from(error: SwapAdapterError) -> Self {
+        VaultError::SwapAdapterError(error)
+    }
 }
 
 impl From<PSP22Error> for VaultError {
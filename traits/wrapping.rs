@@ -0,0 +1,89 @@
+use brush::{
+    contracts::traits::psp22::PSP22Error,
+    traits::{AccountId, Balance},
+};
+
+#[brush::wrapper]
+pub type WrappingRef = dyn Wrapping + WrappingView;
+
+/// Tokenized vault of one PSP22 asset (like ERC-4626).
+/// Shares keep their balances, value of a share follows total_assets held by the wrapper.
+#[brush::trait_definition]
+pub trait Wrapping {
+    // transfers assets from caller and mints shares to receiver, returns minted shares
+    #[ink(message)]
+    fn deposit(&mut self, assets: Balance, receiver: AccountId) -> Result<Balance, WrappingError>;
+
+    // mints exactly shares to receiver for assets taken from caller, returns taken assets
+    // assets include a few units of rounding slack, so the wrapper receives at least the value of the shares
+    #[ink(message)]
+    fn mint(&mut self, shares: Balance, receiver: AccountId) -> Result<Balance, WrappingError>;
+
+    // burns shares of owner and transfers exactly assets to receiver, returns burned shares
+    #[ink(message)]
+    fn withdraw(
+        &mut self,
+        assets: Balance,
+        receiver: AccountId,
+        owner: AccountId,
+    ) -> Result<Balance, WrappingError>;
+
+    // burns exactly shares of owner and transfers assets to receiver, returns transfered assets
+    #[ink(message)]
+    fn redeem(
+        &mut self,
+        shares: Balance,
+        receiver: AccountId,
+        owner: AccountId,
+    ) -> Result<Balance, WrappingError>;
+}
+
+#[brush::trait_definition]
+pub trait WrappingView {
+    #[ink(message)]
+    fn asset(&self) -> AccountId;
+
+    #[ink(message)]
+    fn total_assets(&self) -> Balance;
+
+    #[ink(message)]
+    fn convert_to_shares(&self, assets: Balance) -> Balance;
+
+    #[ink(message)]
+    fn convert_to_assets(&self, shares: Balance) -> Balance;
+
+    #[ink(message)]
+    fn max_withdraw(&self, owner: AccountId) -> Balance;
+
+    #[ink(message)]
+    fn max_redeem(&self, owner: AccountId) -> Balance;
+
+    #[ink(message)]
+    fn preview_deposit(&self, assets: Balance) -> Balance;
+
+    #[ink(message)]
+    fn preview_mint(&self, shares: Balance) -> Balance;
+
+    #[ink(message)]
+    fn preview_withdraw(&self, assets: Balance) -> Balance;
+
+    #[ink(message)]
+    fn preview_redeem(&self, shares: Balance) -> Balance;
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum WrappingError {
+    ZeroAmount,
+    AmountAboveMaximum,
+    NotTaxFree,
+    PSP22Error(PSP22Error),
+    Overflow,
+    InsufficientAssetsReceived,
+}
+
+impl From<PSP22Error> for WrappingError {
+    fn from(error: PSP22Error) -> Self {
+        WrappingError::PSP22Error(error)
+    }
+}